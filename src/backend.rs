@@ -0,0 +1,1581 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer};
+
+use crate::config::{Config, SeverityOverride};
+use crate::cursor::{self, CursorLocation};
+use crate::document::Document;
+use crate::error::ValidationResult;
+use crate::keywords::CustomKeywords;
+use crate::line_number::{self, PositionEncoding};
+use crate::parsing::{self, ParsedContent, SourceLanguage};
+use crate::resolver::{
+    self, CachedSchemaResolver, DefaultSchemaResolver, SchemaResolver, ScopedSchemaResolver,
+};
+use crate::sink::DiagnosticSink;
+use crate::validation::CompiledSchema;
+use crate::{code_action, completion, highlight, hover, introspection, symbols, validation};
+
+/// LSP Server for Json based LSP config validation
+/// validate against the schema -> errors give {instance_path, schema_path, to_string}
+///
+/// Point Json Error Pointer to an LSP Range struct (at first highlight high level path), expand to
+/// ranges/attempt to find explicit range where error occurs (wait until other functionalities
+/// work well)
+///
+/// create a diagnostic struct to share include stuff like.. range, severity, source, message
+///
+/// # Notes for now
+/// - hard-code the schema path in test cases/have a json field at the top calld $"schema" with
+///   accurate schema
+// Json Schema Type
+type Schema = Arc<serde_json::Value>;
+// schemas are compiled once when loaded, validating a document reuses the compiled validator
+type JsonSchemas = Arc<RwLock<HashMap<String, Arc<CompiledSchema>>>>;
+// latest full text of each open document, needed for position based requests
+type Documents = Arc<RwLock<HashMap<Url, String>>>;
+// language id each document was opened with, did_change does not repeat it
+type Languages = Arc<RwLock<HashMap<Url, String>>>;
+
+/// Schema documents are validated against when they declare none and no schema is
+/// configured for their language id
+const DEFAULT_SCHEMA: &str = "service.schema";
+
+/// `workspace/executeCommand` name revalidating every open document
+pub const VALIDATE_ALL_COMMAND: &str = "pur.validateAll";
+
+/// `workspace/executeCommand` name validating only the value under a selection,
+/// arguments are the document uri and the selected range
+pub const VALIDATE_SELECTION_COMMAND: &str = "pur.validateSelection";
+
+pub struct Backend {
+    client: Client,
+    // where computed diagnostics go, the client unless replaced for embedding
+    sink: Arc<dyn DiagnosticSink>,
+    // extension keywords every loaded schema is compiled with
+    keywords: CustomKeywords,
+    // rust analyzer uses same pattern with Arc RwLock -- Frequestn Read, Infrequesnt writes
+    // wrapped json value in Arc for shared ownership in the heap.. value should not change
+    json_schemas: JsonSchemas,
+    documents: Documents,
+    languages: Languages,
+    // where schemas not yet in json_schemas are loaded from, replaceable through config
+    resolver: RwLock<Arc<dyn SchemaResolver>>,
+    config: Arc<RwLock<Config>>,
+    // local paths of the client's workspace folders, `schema_dir` is relative to them
+    workspace_folders: RwLock<Vec<PathBuf>>,
+    // encoding positions are exchanged in with the client, positions are UTF-16 internally
+    position_encoding: RwLock<PositionEncoding>,
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    // TODO load json schema for given config file on either initialize or new document was opened.
+    // FOR now only implement intitialize, textDocument{didOpen, didChange, }, and
+    // publishDiagnostics
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        #[allow(deprecated)] // root_uri is the fallback for clients without workspace folders
+        let folders = match params.workspace_folders {
+            Some(folders) => folders.into_iter().map(|folder| folder.uri).collect(),
+            None => params.root_uri.into_iter().collect::<Vec<_>>(),
+        };
+        *self.workspace_folders.write().await = folders
+            .iter()
+            .filter_map(|uri| uri.to_file_path().ok())
+            .collect();
+
+        let encoding = PositionEncoding::negotiate(
+            params
+                .capabilities
+                .general
+                .as_ref()
+                .and_then(|general| general.position_encodings.as_deref()),
+        );
+        *self.position_encoding.write().await = encoding;
+
+        if let Some(options) = params.initialization_options {
+            match serde_json::from_value::<Config>(options) {
+                Ok(config) => {
+                    self.use_configured_resolver(&config).await;
+                    *self.config.write().await = config;
+                }
+                Err(e) => eprintln!("Invalid initialization options, using defaults: {}", e),
+            }
+        }
+
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                position_encoding: Some(encoding.kind()),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                completion_provider: Some(CompletionOptions::default()),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        VALIDATE_ALL_COMMAND.to_string(),
+                        VALIDATE_SELECTION_COMMAND.to_string(),
+                    ],
+                    ..Default::default()
+                }),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions::default(),
+                )),
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.client
+            .log_message(MessageType::INFO, "server initialized!")
+            .await;
+    }
+
+    // handle did_open, did_change the same way (send whole document at once)
+    // later improve this... sync state
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        self.languages.write().await.insert(
+            params.text_document.uri.clone(),
+            params.text_document.language_id.clone(),
+        );
+
+        self.on_change(OnChangeTextDocumentParams {
+            uri: params.text_document.uri,
+            text: &params.text_document.text,
+            version: Some(params.text_document.version),
+        })
+        .await
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        self.on_change(OnChangeTextDocumentParams {
+            uri: params.text_document.uri,
+            text: &params.content_changes[0].text,
+            version: Some(params.text_document.version),
+        })
+        .await
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.documents
+            .write()
+            .await
+            .remove(&params.text_document.uri);
+        self.languages
+            .write()
+            .await
+            .remove(&params.text_document.uri);
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        let uri = &position.text_document.uri;
+        let Some(raw_text) = self.documents.read().await.get(uri).cloned() else {
+            return Ok(None);
+        };
+        // comments may hold braces and quotes the cursor scan would take for structure
+        let text = self.language_of(uri).await.normalize(&raw_text);
+        let at = self.internal_position(&raw_text, position.position).await;
+        let Some(context) = cursor::context_at(&text, at) else {
+            return Ok(None);
+        };
+        let schema_id = self.schema_id_for(uri, &raw_text).await;
+
+        let Ok(schema) = self
+            .get_or_load_schema(&position.text_document.uri, &schema_id)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let completions = match context.location {
+            CursorLocation::Key => completion::property_completions,
+            CursorLocation::Value => completion::value_completions,
+        };
+
+        Ok(introspection::resolve(&schema, &context.pointer)
+            .map(completions)
+            .map(CompletionResponse::Array))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let Some(raw_text) = self.documents.read().await.get(&uri).cloned() else {
+            return Ok(None);
+        };
+        // normalized text keeps every LSP position, so edits computed on it apply to the original
+        let text = self
+            .language_of(&uri)
+            .await
+            .normalize(&raw_text)
+            .into_owned();
+        let Ok(ParsedContent::Valid(json)) = ParsedContent::new(&text) else {
+            return Ok(None);
+        };
+        let schema_id = self.schema_id_for(&uri, &raw_text).await;
+        let Ok(schema) = self.get_or_load_schema(&uri, &schema_id).await else {
+            return Ok(None);
+        };
+        let Ok(fixes) = code_action::safe_fixes(&schema, &json, &text) else {
+            return Ok(None);
+        };
+        let document = Document::new(&text);
+
+        let encoding = *self.position_encoding.read().await;
+        let requested = Range {
+            start: line_number::convert(
+                &raw_text,
+                params.range.start,
+                encoding,
+                PositionEncoding::Utf16,
+            ),
+            end: line_number::convert(
+                &raw_text,
+                params.range.end,
+                encoding,
+                PositionEncoding::Utf16,
+            ),
+        };
+
+        let suggestions = params
+            .context
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| code_action::suggestion_fix(diagnostic, &text));
+
+        let actions = fixes
+            .into_iter()
+            .chain(suggestions)
+            .filter(|fix| {
+                let range = fix.range(&document);
+                range.start <= requested.end && requested.start <= range.end
+            })
+            .map(|fix| {
+                let mut edit = fix.to_text_edit(&document);
+                edit.range = client_range(&raw_text, edit.range, encoding);
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: fix.title.clone(),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        match params.command.as_str() {
+            VALIDATE_ALL_COMMAND => {
+                self.validate_all_open().await;
+                Ok(None)
+            }
+            VALIDATE_SELECTION_COMMAND => {
+                let (uri, range) = match params.arguments.as_slice() {
+                    [uri, range] => (
+                        serde_json::from_value::<Url>(uri.clone()),
+                        serde_json::from_value::<Range>(range.clone()),
+                    ),
+                    _ => {
+                        return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                            "Expected the document uri and the selected range",
+                        ));
+                    }
+                };
+                let (Ok(uri), Ok(range)) = (uri, range) else {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                        "Invalid document uri or selected range",
+                    ));
+                };
+                self.validate_selection(uri, range).await;
+                Ok(None)
+            }
+            command => Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "Unknown command {}",
+                command
+            ))),
+        }
+    }
+
+    // pull model counterpart of the diagnostics on_change publishes
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let uri = params.text_document.uri;
+        let text = self.documents.read().await.get(&uri).cloned();
+
+        let items = match text {
+            Some(text) => self
+                .compute_diagnostics(&uri, &text)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("Error Schema Validation @ {}: {}", uri, e);
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+
+        Ok(DocumentDiagnosticReportResult::Report(
+            DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                    result_id: None,
+                    items,
+                },
+            }),
+        ))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let uri = &position.text_document.uri;
+        let Some(raw_text) = self.documents.read().await.get(uri).cloned() else {
+            return Ok(None);
+        };
+        let text = self.language_of(uri).await.normalize(&raw_text);
+        let at = self.internal_position(&raw_text, position.position).await;
+        let Some(pointer) = cursor::pointer_at(&text, at) else {
+            return Ok(None);
+        };
+        let schema_id = self.schema_id_for(uri, &raw_text).await;
+        let Ok(schema) = self
+            .get_or_load_schema(&position.text_document.uri, &schema_id)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        Ok(introspection::resolve(&schema, &pointer)
+            .and_then(hover::markdown)
+            .map(|value| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            }))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri;
+        let Some(raw_text) = self.documents.read().await.get(&uri).cloned() else {
+            return Ok(None);
+        };
+        let text = self.language_of(&uri).await.normalize(&raw_text);
+        // the outline does not need a schema, only the details come from it
+        let schema_id = self.schema_id_for(&uri, &raw_text).await;
+        let schema = self.get_or_load_schema(&uri, &schema_id).await.ok();
+
+        let mut symbols = symbols::document_symbols(schema.as_deref(), &text);
+        let encoding = *self.position_encoding.read().await;
+        client_symbol_ranges(&raw_text, &mut symbols, encoding);
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let position = params.text_document_position_params;
+        let uri = &position.text_document.uri;
+        let Some(raw_text) = self.documents.read().await.get(uri).cloned() else {
+            return Ok(None);
+        };
+        let text = self.language_of(uri).await.normalize(&raw_text);
+
+        let encoding = *self.position_encoding.read().await;
+        let at = line_number::convert(
+            &raw_text,
+            position.position,
+            encoding,
+            PositionEncoding::Utf16,
+        );
+        let highlights: Vec<DocumentHighlight> = highlight::key_occurrences(&text, at)
+            .into_iter()
+            .map(|range| DocumentHighlight {
+                range: client_range(&raw_text, range, encoding),
+                kind: Some(DocumentHighlightKind::TEXT),
+            })
+            .collect();
+
+        Ok((!highlights.is_empty()).then_some(highlights))
+    }
+}
+
+/// The document parsed, when it is a Json Schema itself: named `*.schema.json`
+/// or declaring a json-schema.org meta-schema
+fn schema_document(uri: &Url, text: &str) -> Option<serde_json::Value> {
+    let json: serde_json::Value = serde_json::from_str(text).ok()?;
+    let meta_schema = json
+        .get("$schema")
+        .and_then(serde_json::Value::as_str)
+        .is_some_and(|id| id.contains("json-schema.org"));
+
+    (meta_schema || uri.path().ends_with(".schema.json")).then_some(json)
+}
+
+/// Internally computed (UTF-16) range re-expressed in the negotiated `encoding`
+fn client_range(raw_text: &str, range: Range, encoding: PositionEncoding) -> Range {
+    Range {
+        start: line_number::convert(raw_text, range.start, PositionEncoding::Utf16, encoding),
+        end: line_number::convert(raw_text, range.end, PositionEncoding::Utf16, encoding),
+    }
+}
+
+fn client_symbol_ranges(
+    raw_text: &str,
+    symbols: &mut [DocumentSymbol],
+    encoding: PositionEncoding,
+) {
+    for symbol in symbols {
+        symbol.range = client_range(raw_text, symbol.range, encoding);
+        symbol.selection_range = client_range(raw_text, symbol.selection_range, encoding);
+        if let Some(children) = &mut symbol.children {
+            client_symbol_ranges(raw_text, children, encoding);
+        }
+    }
+}
+
+struct OnChangeTextDocumentParams<'document_text> {
+    uri: Url,
+    text: &'document_text str,
+    version: Option<i32>,
+}
+
+impl Backend {
+    pub fn new(client: Client) -> Self {
+        Self::with_resolver(client, Arc::new(DefaultSchemaResolver::default()))
+    }
+
+    /// Creates a Backend loading schemas through a custom resolver, for embedding
+    pub fn with_resolver(client: Client, resolver: Arc<dyn SchemaResolver>) -> Self {
+        Self {
+            sink: Arc::new(client.clone()),
+            keywords: CustomKeywords::default(),
+            client,
+            json_schemas: JsonSchemas::default(),
+            documents: Documents::default(),
+            languages: Languages::default(),
+            resolver: RwLock::new(resolver),
+            config: Arc::default(),
+            workspace_folders: RwLock::default(),
+            position_encoding: RwLock::default(),
+        }
+    }
+
+    /// Publishes diagnostics to `sink` instead of the client
+    pub fn with_sink(mut self, sink: Arc<dyn DiagnosticSink>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    /// Validates the extension keywords registered in `keywords` in every schema
+    pub fn with_keywords(mut self, keywords: CustomKeywords) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Loads and compiles the default schema ahead of the first document, so a broken
+    /// schema is reported at startup instead of on the first edit
+    pub async fn warm_up(&self) -> ValidationResult<()> {
+        self.preload_schemas(&[DEFAULT_SCHEMA.to_owned()]).await
+    }
+
+    /// Loads and compiles `ids` through the configured resolver into the schema cache,
+    /// so the first document using one of them does not pay for it
+    /// Stops at the first schema failing to load or compile
+    pub async fn preload_schemas(&self, ids: &[String]) -> ValidationResult<()> {
+        let resolver = self.resolver.read().await.clone();
+        for id in ids {
+            self.get_or_load_compiled(id.clone(), resolver.as_ref(), id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Swaps in the schema source requested by `config`, if any, and wraps it in an
+    /// offline cache when `schema_cache_dir` is set
+    async fn use_configured_resolver(&self, config: &Config) {
+        if let Some(bundle) = &config.schema_bundle {
+            #[cfg(feature = "zip")]
+            {
+                *self.resolver.write().await =
+                    Arc::new(crate::resolver::BundleSchemaResolver::open(bundle.clone()));
+            }
+            #[cfg(not(feature = "zip"))]
+            eprintln!(
+                "Schema bundle {} ignored, built without the zip feature",
+                bundle.display()
+            );
+        }
+
+        if let Some(cache_dir) = &config.schema_cache_dir {
+            let mut resolver = self.resolver.write().await;
+            *resolver = Arc::new(CachedSchemaResolver::new(
+                resolver.clone(),
+                cache_dir.clone(),
+            ));
+        }
+    }
+
+    /// this is the entry point for validating content
+    /// on change is called on document text change... as well as
+    async fn on_change<'document_text>(&self, params: OnChangeTextDocumentParams<'document_text>) {
+        self.documents
+            .write()
+            .await
+            .insert(params.uri.clone(), params.text.to_owned());
+
+        match self.compute_diagnostics(&params.uri, params.text).await {
+            // publish diagnostics to client
+            Ok(diagnostics) => {
+                self.sink
+                    .publish(params.uri.as_str(), diagnostics, params.version)
+                    .await;
+            }
+            Err(e) => {
+                eprintln!("Error @ {} Version:{:?}: {}", params.uri, params.version, e);
+            }
+        };
+    }
+
+    /// Revalidates every open document and republishes its diagnostics
+    /// Returns the documents diagnostics were published for
+    async fn validate_all_open(&self) -> Vec<Url> {
+        // snapshot so validation does not hold the documents lock
+        let documents = self.documents.read().await.clone();
+        let mut published = Vec::with_capacity(documents.len());
+
+        for (uri, text) in documents {
+            match self.compute_diagnostics(&uri, &text).await {
+                Ok(diagnostics) => {
+                    self.sink.publish(uri.as_str(), diagnostics, None).await;
+                    published.push(uri);
+                }
+                Err(e) => eprintln!("Error @ {}: {}", uri, e),
+            }
+        }
+
+        published
+    }
+
+    /// Validates the value enclosing `range` of the open document at `uri` against its
+    /// sub-schema, publishing only those diagnostics
+    async fn validate_selection(&self, uri: Url, range: Range) {
+        let Some(raw_text) = self.documents.read().await.get(&uri).cloned() else {
+            return;
+        };
+        let text = self.language_of(&uri).await.normalize(&raw_text);
+
+        let encoding = *self.position_encoding.read().await;
+        let range = Range {
+            start: line_number::convert(&raw_text, range.start, encoding, PositionEncoding::Utf16),
+            end: line_number::convert(&raw_text, range.end, encoding, PositionEncoding::Utf16),
+        };
+        let Some(pointer) = cursor::pointer_enclosing(&text, range) else {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Selection @ {} is not inside a JSON value", uri),
+                )
+                .await;
+            return;
+        };
+
+        let schema_id = self.schema_id_for(&uri, &raw_text).await;
+        let diagnostics = match self.get_or_load_schema(&uri, &schema_id).await {
+            Ok(schema) => crate::validate_fragment(&schema, &text, &pointer),
+            Err(e) => Err(e),
+        };
+        match diagnostics {
+            Ok(mut diagnostics) => {
+                for diagnostic in &mut diagnostics {
+                    diagnostic.range = client_range(&raw_text, diagnostic.range, encoding);
+                }
+                self.sink.publish(uri.as_str(), diagnostics, None).await;
+            }
+            Err(e) => {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Error validating selection @ {}: {}", uri, e),
+                    )
+                    .await
+            }
+        }
+    }
+
+    /// Diagnostics for `text` as the document at `uri`, shared by the push (on_change)
+    /// and pull (diagnostic) models so both always report the same thing
+    async fn compute_diagnostics(
+        &self,
+        uri: &Url,
+        text: &str,
+    ) -> ValidationResult<Vec<Diagnostic>> {
+        // todo improve schema_validated_filecontents later
+        let schema_id = self.schema_id_for(uri, text).await;
+        let schema = self.get_or_load_schema_compiled(uri, &schema_id).await?;
+        let raw_text = text;
+        let text = self.language_of(uri).await.normalize(text);
+
+        let config = self.config.read().await;
+        let mut diagnostics = schema.validate_with_schema_id(&schema_id, &text, &config)?;
+        if config.related_information {
+            validation::link_related(&mut diagnostics, uri);
+        }
+        if config.warn_unknown_formats {
+            diagnostics.extend(schema.unknown_format_notices(&schema_id));
+        }
+        if (config.warn_unknown_formats || config.warn_dangling_refs)
+            && let Some(json_schema) = schema_document(uri, &text)
+        {
+            if config.warn_unknown_formats {
+                diagnostics.extend(validation::unknown_formats(&json_schema, &text));
+            }
+            if config.warn_dangling_refs {
+                diagnostics.extend(validation::dangling_refs(&json_schema, &text));
+            }
+        }
+
+        if let Some(severity) = self
+            .severity_override_for(uri, &config.severity_overrides)
+            .await
+        {
+            for diagnostic in &mut diagnostics {
+                if diagnostic.severity == Some(DiagnosticSeverity::ERROR) {
+                    diagnostic.severity = Some(severity);
+                }
+            }
+        }
+
+        let resolver = self.resolver.read().await.clone();
+        diagnostics.extend(resolver.notices(&schema_id).await);
+
+        let encoding = *self.position_encoding.read().await;
+        for diagnostic in &mut diagnostics {
+            diagnostic.range = client_range(raw_text, diagnostic.range, encoding);
+            for related in diagnostic.related_information.iter_mut().flatten() {
+                if &related.location.uri == uri {
+                    related.location.range =
+                        client_range(raw_text, related.location.range, encoding);
+                }
+            }
+        }
+        Ok(diagnostics)
+    }
+
+    /// Dialect of the open document at `uri`, plain JSON unless opened with a known language id
+    async fn language_of(&self, uri: &Url) -> SourceLanguage {
+        self.languages
+            .read()
+            .await
+            .get(uri)
+            .map(|language_id| SourceLanguage::from_language_id(language_id))
+            .unwrap_or_default()
+    }
+
+    /// Id of the schema the document at `uri` is validated against: the one it declares
+    /// (`$schema` or shebang), else the one configured for its language id, else the default
+    /// A json-schema.org meta-schema marks a schema document, it is not loaded in its place
+    async fn schema_id_for(&self, uri: &Url, raw_text: &str) -> String {
+        let text = self.language_of(uri).await.normalize(raw_text);
+        if let Some(declared) = parsing::declared_schema(&text)
+            && !declared.contains("json-schema.org")
+        {
+            return declared;
+        }
+
+        let languages = self.languages.read().await;
+        let config = self.config.read().await;
+        languages
+            .get(uri)
+            .and_then(|language_id| config.language_schemas.get(language_id))
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_SCHEMA.to_owned())
+    }
+
+    /// Position received from the client, in the negotiated encoding, as a UTF-16 one
+    async fn internal_position(&self, raw_text: &str, position: Position) -> Position {
+        let encoding = *self.position_encoding.read().await;
+        line_number::convert(raw_text, position, encoding, PositionEncoding::Utf16)
+    }
+
+    // for now only load schema hard coded
+    // TODO discover schema from text, then search hashmap, then try to load from source somewhere
+    async fn get_or_load_schema(&self, uri: &Url, key: &str) -> ValidationResult<Schema> {
+        let compiled = self.get_or_load_schema_compiled(uri, key).await?;
+        Ok(compiled.json_schema().clone())
+    }
+
+    /// Compiled schema `key` for the document at `uri`
+    async fn get_or_load_schema_compiled(
+        &self,
+        uri: &Url,
+        key: &str,
+    ) -> ValidationResult<Arc<CompiledSchema>> {
+        let normalized;
+        let key = if self.config.read().await.normalize_schema_ids {
+            normalized = resolver::normalize_schema_id(key);
+            normalized.as_str()
+        } else {
+            key
+        };
+
+        // folder relative schemas are cached per directory, the same id differs between folders
+        match self.schema_dir_for(uri).await {
+            Some(dir) => {
+                let cache_key = dir.join(key).display().to_string();
+                let resolver = ScopedSchemaResolver::new(self.resolver.read().await.clone(), dir);
+                self.get_or_load_compiled(cache_key, &resolver, key).await
+            }
+            None => {
+                let resolver = self.resolver.read().await.clone();
+                self.get_or_load_compiled(key.to_owned(), resolver.as_ref(), key)
+                    .await
+            }
+        }
+    }
+
+    /// Cached schema under `cache_key`, otherwise resolves `key` and compiles it eagerly
+    /// A forced draft is part of the cache key, the same schema compiles differently per draft
+    async fn get_or_load_compiled(
+        &self,
+        cache_key: String,
+        resolver: &dyn SchemaResolver,
+        key: &str,
+    ) -> ValidationResult<Arc<CompiledSchema>> {
+        let draft = self.config.read().await.draft;
+        let cache_key = match draft {
+            Some(draft) => format!("{}@{:?}", cache_key, draft),
+            None => cache_key,
+        };
+
+        // search for existing.. if not found add
+        {
+            let schemas = self.json_schemas.read().await;
+            if let Some(schema) = schemas.get(&cache_key) {
+                // cheap clone only reference
+                return Ok(schema.clone());
+            }
+        }
+
+        let schema = Arc::new(CompiledSchema::load(resolver, key, &self.keywords, draft).await?);
+
+        // write with lock + clone schema so it can be returned
+        let mut schemas = self.json_schemas.write().await;
+        Ok(schemas.entry(cache_key).or_insert(schema).clone())
+    }
+
+    /// Severity of the first override whose glob matches the document at `uri`
+    /// Invalid globs match nothing
+    async fn severity_override_for(
+        &self,
+        uri: &Url,
+        overrides: &[SeverityOverride],
+    ) -> Option<DiagnosticSeverity> {
+        if overrides.is_empty() {
+            return None;
+        }
+        let path = uri.to_file_path().ok()?;
+        let folders = self.workspace_folders.read().await;
+        let relative: Vec<&std::path::Path> = folders
+            .iter()
+            .filter_map(|folder| path.strip_prefix(folder).ok())
+            .collect();
+
+        overrides
+            .iter()
+            .find(|o| {
+                o.glob.matches(&path) || relative.iter().any(|relative| o.glob.matches(relative))
+            })
+            .map(|o| o.severity.to_lsp())
+    }
+
+    /// Configured `schema_dir` for the document at `uri`, joined onto its workspace folder
+    /// None when no directory is configured, the configured resolver is used then
+    async fn schema_dir_for(&self, uri: &Url) -> Option<PathBuf> {
+        let schema_dir = self.config.read().await.schema_dir.clone()?;
+        if schema_dir.is_absolute() {
+            return Some(schema_dir);
+        }
+
+        let document = uri.to_file_path().ok();
+        let folders = self.workspace_folders.read().await;
+        // nested folders: the deepest one containing the document wins
+        let folder = folders
+            .iter()
+            .filter(|folder| {
+                document
+                    .as_ref()
+                    .is_some_and(|path| path.starts_with(folder))
+            })
+            .max_by_key(|folder| folder.components().count());
+
+        Some(match folder {
+            Some(folder) => folder.join(schema_dir),
+            None => schema_dir,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower_lsp::{ClientSocket, LspService};
+
+    use crate::config::SchemaDraft;
+    use crate::error::SchemaValidationError;
+    use crate::test_util::TempDir;
+
+    /// Serves schemas from a map, counting every resolve call
+    #[derive(Default)]
+    struct InMemoryResolver {
+        schemas: HashMap<String, serde_json::Value>,
+        resolve_count: AtomicUsize,
+    }
+
+    #[tower_lsp::async_trait]
+    impl SchemaResolver for InMemoryResolver {
+        async fn resolve(&self, id: &str) -> ValidationResult<Arc<serde_json::Value>> {
+            self.resolve_count.fetch_add(1, Ordering::SeqCst);
+            self.schemas
+                .get(id)
+                .cloned()
+                .map(Arc::new)
+                .ok_or_else(|| SchemaValidationError::SchemaNotFound(id.to_string()))
+        }
+    }
+
+    // uri, diagnostics and version of one publish
+    type Publish = (String, Vec<Diagnostic>, Option<i32>);
+
+    /// Records every publish instead of sending it to a client
+    #[derive(Default)]
+    struct RecordingSink {
+        published: std::sync::Mutex<Vec<Publish>>,
+    }
+
+    #[tower_lsp::async_trait]
+    impl DiagnosticSink for RecordingSink {
+        async fn publish(&self, uri: &str, diagnostics: Vec<Diagnostic>, version: Option<i32>) {
+            self.published
+                .lock()
+                .unwrap()
+                .push((uri.to_owned(), diagnostics, version));
+        }
+    }
+
+    /// Backend resolving schemas through an `InMemoryResolver`, publishing to a `RecordingSink`
+    struct TestServer {
+        service: LspService<Backend>,
+        _socket: ClientSocket,
+        resolver: Arc<InMemoryResolver>,
+        sink: Arc<RecordingSink>,
+    }
+
+    impl TestServer {
+        fn inner(&self) -> &Backend {
+            self.service.inner()
+        }
+    }
+
+    /// Test server serving each `(id, schema)` of `schemas`
+    fn backend_with_schemas<const N: usize>(schemas: [(&str, serde_json::Value); N]) -> TestServer {
+        let resolver = Arc::new(InMemoryResolver {
+            schemas: schemas
+                .into_iter()
+                .map(|(id, schema)| (id.to_string(), schema))
+                .collect(),
+            ..Default::default()
+        });
+        let sink = Arc::new(RecordingSink::default());
+        let (service, _socket) = LspService::new({
+            let (resolver, sink) = (resolver.clone(), sink.clone());
+            |client| Backend::with_resolver(client, resolver).with_sink(sink)
+        });
+
+        TestServer {
+            service,
+            _socket,
+            resolver,
+            sink,
+        }
+    }
+
+    /// Test server validating every document against `schema`
+    fn backend_with_schema(schema: serde_json::Value) -> TestServer {
+        backend_with_schemas([(DEFAULT_SCHEMA, schema)])
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_are_published_to_sink() {
+        let server = backend_with_schema(serde_json::json!({ "required": ["service"] }));
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "json".into(), 3, "{}".into()),
+            })
+            .await;
+        backend.validate_all_open().await;
+
+        let published = server.sink.published.lock().unwrap();
+        let versions: Vec<Option<i32>> = published.iter().map(|(_, _, v)| *v).collect();
+        assert_eq!(versions, vec![Some(3), None]);
+        assert!(
+            published.iter().all(
+                |(published_uri, diagnostics, _)| published_uri == uri.as_str()
+                    && diagnostics.len() == 1
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_selection_publishes_only_its_subtree() {
+        let server = backend_with_schema(serde_json::json!({
+            "definitions": { "port": { "type": "integer" } },
+            "properties": {
+                "api": { "properties": { "port": { "$ref": "#/definitions/port" } } },
+                "admin": { "properties": { "port": { "$ref": "#/definitions/port" } } }
+            }
+        }));
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        let text = "{\n  \"api\": { \"port\": \"80\" },\n  \"admin\": { \"port\": \"81\" }\n}";
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "json".into(), 1, text.into()),
+            })
+            .await;
+
+        // selects the `{ "port": "81" }` object of "admin"
+        let selection = Range::new(Position::new(2, 11), Position::new(2, 27));
+        backend
+            .execute_command(ExecuteCommandParams {
+                command: VALIDATE_SELECTION_COMMAND.to_string(),
+                arguments: vec![
+                    serde_json::to_value(&uri).unwrap(),
+                    serde_json::to_value(selection).unwrap(),
+                ],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let published = server.sink.published.lock().unwrap();
+        let sources = |diagnostics: &[Diagnostic]| -> Vec<String> {
+            diagnostics
+                .iter()
+                .filter_map(|d| d.source.clone())
+                .collect()
+        };
+        assert_eq!(published[0].1.len(), 2);
+        assert_eq!(sources(&published[1].1), vec!["/admin/port"]);
+        assert_eq!(published[1].1[0].range.start.line, 2);
+    }
+
+    #[tokio::test]
+    async fn test_push_and_pull_diagnostics_match() {
+        let server = backend_with_schema(
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap(),
+        );
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.jsonc").unwrap();
+        let text = include_str!("../testing/test_additional_property.json");
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "jsonc".into(), 1, text.into()),
+            })
+            .await;
+
+        let (published_uri, pushed, _) = server.sink.published.lock().unwrap().remove(0);
+        assert_eq!(published_uri, uri.as_str());
+        assert!(!pushed.is_empty());
+
+        let pulled = backend
+            .diagnostic(DocumentDiagnosticParams {
+                text_document: TextDocumentIdentifier::new(uri),
+                identifier: None,
+                previous_result_id: None,
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap();
+        let DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report)) = pulled
+        else {
+            panic!("expected a full diagnostic report");
+        };
+        assert_eq!(report.full_document_diagnostic_report.items, pushed);
+    }
+
+    #[tokio::test]
+    async fn test_completion_and_hover_ignore_jsonc_comments() {
+        let server = backend_with_schema(serde_json::json!({
+            "properties": { "name": { "type": "string", "description": "Service name" } }
+        }));
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.jsonc").unwrap();
+        // without stripping, the comment opens an object `legacy` around the keys below
+        let text = "{\n  // \"legacy\": {\n  \"name\": \"api\",\n  \n}";
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "jsonc".into(), 1, text.into()),
+            })
+            .await;
+        let at = |line, character| {
+            TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(uri.clone()),
+                Position::new(line, character),
+            )
+        };
+
+        let hover = backend
+            .hover(HoverParams {
+                text_document_position_params: at(2, 4),
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .expect("hover on name");
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown hover");
+        };
+        assert!(markup.value.contains("Service name"));
+
+        let completions = backend
+            .completion(CompletionParams {
+                text_document_position: at(3, 2),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                context: None,
+            })
+            .await
+            .unwrap();
+        let Some(CompletionResponse::Array(items)) = completions else {
+            panic!("expected completions");
+        };
+        assert_eq!(items[0].label, "name");
+    }
+
+    #[tokio::test]
+    async fn test_highlights_keys_of_jsonc_documents() {
+        let server = backend_with_schema(serde_json::json!({}));
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///ports.jsonc").unwrap();
+        let text = r#"{
+  // "port": "in a comment",
+  "ports": [
+    { "port": 8080 }, /* { "port": 0 } */
+    { "port": 9090 }
+  ]
+}"#;
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri.clone(), "jsonc".into(), 1, text.into()),
+            })
+            .await;
+
+        let highlights = backend
+            .document_highlight(DocumentHighlightParams {
+                text_document_position_params: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri),
+                    Position::new(3, 8),
+                ),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .expect("highlights on port");
+        let lines: Vec<u32> = highlights.iter().map(|h| h.range.start.line).collect();
+        assert_eq!(lines, vec![3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_schema_selected_per_document() {
+        let server = backend_with_schemas([
+            (
+                DEFAULT_SCHEMA,
+                serde_json::json!({ "required": ["service"] }),
+            ),
+            (
+                "worker.schema",
+                serde_json::json!({ "required": ["queue"] }),
+            ),
+            (
+                "tsconfig.schema",
+                serde_json::json!({ "required": ["compilerOptions"] }),
+            ),
+        ]);
+        let backend = server.inner();
+        backend
+            .config
+            .write()
+            .await
+            .language_schemas
+            .insert("jsonc".to_string(), "tsconfig.schema".to_string());
+
+        let documents = [
+            ("file:///service.json", "json", "{}"),
+            // the declared schema wins over the one configured for the language
+            (
+                "file:///worker.jsonc",
+                "jsonc",
+                "{\n  // queue worker\n  \"$schema\": \"worker.schema\"\n}",
+            ),
+            ("file:///tsconfig.json", "jsonc", "{ /* no schema */ }"),
+        ];
+        for (uri, language_id, text) in documents {
+            backend
+                .did_open(DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem::new(
+                        Url::parse(uri).unwrap(),
+                        language_id.into(),
+                        1,
+                        text.into(),
+                    ),
+                })
+                .await;
+        }
+
+        let published = server.sink.published.lock().unwrap();
+        let messages: Vec<&str> = published
+            .iter()
+            .map(|(_, diagnostics, _)| diagnostics[0].message.as_str())
+            .collect();
+        assert_eq!(published.len(), 3);
+        assert!(messages[0].contains("\"service\""), "{}", messages[0]);
+        assert!(messages[1].contains("\"queue\""), "{}", messages[1]);
+        assert!(
+            messages[2].contains("\"compilerOptions\""),
+            "{}",
+            messages[2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_all_republishes_every_open_document() {
+        let server = backend_with_schema(serde_json::json!({ "required": ["name"] }));
+        let backend = server.inner();
+
+        let uris: Vec<Url> = ["file:///a.json", "file:///b.json"]
+            .iter()
+            .map(|uri| Url::parse(uri).unwrap())
+            .collect();
+        for uri in &uris {
+            backend
+                .did_open(DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem::new(
+                        uri.clone(),
+                        "json".into(),
+                        1,
+                        "{}".into(),
+                    ),
+                })
+                .await;
+        }
+
+        let mut published = backend.validate_all_open().await;
+        published.sort();
+        assert_eq!(published, uris);
+
+        let unknown = backend
+            .execute_command(ExecuteCommandParams {
+                command: "pur.unknown".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(unknown.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_schema_dir_resolves_per_workspace_folder() {
+        let root = TempDir::new("folders");
+        let folders = [root.join("api"), root.join("worker")];
+        for (folder, title) in folders.iter().zip(["Api", "Worker"]) {
+            std::fs::create_dir_all(folder.join("schemas")).unwrap();
+            std::fs::write(
+                folder.join("schemas/app.schema.json"),
+                serde_json::json!({ "title": title }).to_string(),
+            )
+            .unwrap();
+        }
+
+        let (service, _socket) = LspService::new(Backend::new);
+        let backend = service.inner();
+        backend
+            .initialize(InitializeParams {
+                workspace_folders: Some(
+                    folders
+                        .iter()
+                        .map(|folder| WorkspaceFolder {
+                            uri: Url::from_directory_path(folder).unwrap(),
+                            name: folder.display().to_string(),
+                        })
+                        .collect(),
+                ),
+                initialization_options: Some(serde_json::json!({ "schemaDir": "schemas" })),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        for (folder, title) in folders.iter().zip(["Api", "Worker"]) {
+            let uri = Url::from_file_path(folder.join("service.json")).unwrap();
+            let schema = backend
+                .get_or_load_schema(&uri, "app.schema.json")
+                .await
+                .unwrap();
+            assert_eq!(schema["title"], title);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schema_dir_scopes_configured_resolver() {
+        let server = backend_with_schemas([
+            (
+                "/work/schemas/app.json",
+                serde_json::json!({ "title": "Scoped" }),
+            ),
+            ("shared.json", serde_json::json!({ "title": "Shared" })),
+        ]);
+        let backend = server.inner();
+        backend
+            .initialize(InitializeParams {
+                workspace_folders: Some(vec![WorkspaceFolder {
+                    uri: Url::parse("file:///work/").unwrap(),
+                    name: "work".to_string(),
+                }]),
+                initialization_options: Some(serde_json::json!({ "schemaDir": "schemas" })),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let uri = Url::parse("file:///work/service.json").unwrap();
+        for (id, title) in [
+            ("app.json", "Scoped"),
+            // not in the directory, the in-memory resolver still serves it
+            ("shared.json", "Shared"),
+        ] {
+            let schema = backend.get_or_load_schema(&uri, id).await.unwrap();
+            assert_eq!(schema["title"], title);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schema_cache_dir_serves_last_fetched_copy() {
+        let cache_dir = TempDir::new("backend-cache");
+        let uri = Url::parse("file:///service.json").unwrap();
+
+        // the second server's resolver has lost the schema, eg it is offline
+        let servers = [
+            backend_with_schema(serde_json::json!({ "required": ["service"] })),
+            backend_with_schemas([]),
+        ];
+        for server in &servers {
+            let backend = server.inner();
+            backend
+                .initialize(InitializeParams {
+                    initialization_options: Some(
+                        serde_json::json!({ "schemaCacheDir": cache_dir.to_path_buf() }),
+                    ),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            backend
+                .did_open(DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem::new(
+                        uri.clone(),
+                        "json".into(),
+                        1,
+                        "{}".into(),
+                    ),
+                })
+                .await;
+        }
+
+        let online = &servers[0].sink.published.lock().unwrap()[0].1;
+        let offline = &servers[1].sink.published.lock().unwrap()[0].1;
+        assert_eq!(online.len(), 1);
+        assert_eq!(offline.len(), 2);
+        assert_eq!(offline[0], online[0]);
+        assert!(
+            offline[1].message.contains("stale"),
+            "{}",
+            offline[1].message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic_positions_follow_negotiated_encoding() {
+        let uri = Url::parse("file:///service.json").unwrap();
+        let text = "{ \"name\": \"caf\u{e9} \u{1f680}\", \"port\": \"80\" }";
+
+        let mut starts = Vec::new();
+        for offered in [None, Some(vec![PositionEncodingKind::UTF8])] {
+            let server = backend_with_schema(
+                serde_json::json!({ "properties": { "port": { "type": "integer" } } }),
+            );
+            let backend = server.inner();
+            let negotiated = backend
+                .initialize(InitializeParams {
+                    capabilities: ClientCapabilities {
+                        general: Some(GeneralClientCapabilities {
+                            position_encodings: offered,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .await
+                .unwrap()
+                .capabilities
+                .position_encoding;
+
+            let diagnostics = backend.compute_diagnostics(&uri, text).await.unwrap();
+            starts.push((negotiated, diagnostics[0].range.start));
+        }
+
+        // é and the rocket take 3 more UTF-8 bytes than UTF-16 code units
+        assert_eq!(
+            starts,
+            vec![
+                (Some(PositionEncodingKind::UTF16), Position::new(0, 22)),
+                (Some(PositionEncodingKind::UTF8), Position::new(0, 25)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_syntax_errors_in_draft_files_are_warnings() {
+        let server = backend_with_schema(serde_json::json!({ "type": "object" }));
+        let backend = server.inner();
+        backend
+            .initialize(InitializeParams {
+                workspace_folders: Some(vec![WorkspaceFolder {
+                    uri: Url::parse("file:///work/").unwrap(),
+                    name: "work".to_string(),
+                }]),
+                initialization_options: Some(serde_json::json!({
+                    "severityOverrides": [{ "glob": "drafts/**", "severity": "warning" }]
+                })),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let text = "{ \"service\": \"api\" \"port\": 80 }";
+        for (path, severity) in [
+            (
+                "file:///work/drafts/scratch.json",
+                DiagnosticSeverity::WARNING,
+            ),
+            ("file:///work/service.json", DiagnosticSeverity::ERROR),
+        ] {
+            let uri = Url::parse(path).unwrap();
+            let diagnostics = backend.compute_diagnostics(&uri, text).await.unwrap();
+
+            assert!(!diagnostics.is_empty());
+            assert!(
+                diagnostics.iter().all(|d| d.severity == Some(severity)),
+                "{}: {:?}",
+                path,
+                diagnostics
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_reports_broken_default_schema() {
+        let server = backend_with_schema(serde_json::json!({ "type": 12 }));
+
+        assert!(matches!(
+            server.inner().warm_up().await,
+            Err(SchemaValidationError::ValidatorCompilationError(_))
+        ));
+
+        // the embedded default schema compiles and is cached for the first document
+        let (service, _socket) = LspService::new(Backend::new);
+        service.inner().warm_up().await.unwrap();
+        assert!(
+            service
+                .inner()
+                .json_schemas
+                .read()
+                .await
+                .contains_key(DEFAULT_SCHEMA)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_equivalent_schema_urls_share_cache_entry() {
+        let server = backend_with_schemas([(
+            "https://schemas.example.com/service.json",
+            serde_json::json!({ "type": "object" }),
+        )]);
+        let backend = server.inner();
+        backend
+            .initialize(InitializeParams {
+                initialization_options: Some(serde_json::json!({ "normalizeSchemaIds": true })),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        for id in [
+            "https://schemas.example.com/service.json#",
+            "https://Schemas.EXAMPLE.com/service.json",
+            "https://schemas.example.com/service.json#/definitions",
+        ] {
+            backend.get_or_load_schema(&uri, id).await.unwrap();
+        }
+
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 1);
+        assert_eq!(backend.json_schemas.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_forced_draft_gets_its_own_cache_entry() {
+        let server = backend_with_schemas([("in-memory", serde_json::json!({ "type": "object" }))]);
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        backend.get_or_load_schema(&uri, "in-memory").await.unwrap();
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 1);
+
+        backend.config.write().await.draft = Some(SchemaDraft::Draft7);
+        backend.get_or_load_schema(&uri, "in-memory").await.unwrap();
+        // recompiled for the forced draft, the entry for the detected draft stays
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 2);
+        assert_eq!(backend.json_schemas.read().await.len(), 2);
+
+        backend.get_or_load_schema(&uri, "in-memory").await.unwrap();
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_remote_refs_resolve_through_configured_resolver() {
+        let server = backend_with_schemas([
+            (
+                DEFAULT_SCHEMA,
+                serde_json::json!({
+                    "properties": { "runtime": { "$ref": "https://example.com/runtime.json" } }
+                }),
+            ),
+            (
+                "broken",
+                serde_json::json!({
+                    "properties": { "ports": { "$ref": "https://example.com/ports.json" } }
+                }),
+            ),
+            (
+                "https://example.com/runtime.json",
+                serde_json::json!({ "type": "object" }),
+            ),
+        ]);
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        let diagnostics = backend
+            .compute_diagnostics(&uri, r#"{ "runtime": 1 }"#)
+            .await
+            .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("object"));
+
+        let Err(e) = backend.get_or_load_schema(&uri, "broken").await else {
+            panic!("ports.json is not served, compiling must fail");
+        };
+        assert!(
+            e.to_string().contains("https://example.com/ports.json"),
+            "{}",
+            e
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preloaded_schemas_are_not_loaded_again() {
+        let server = backend_with_schemas([
+            (
+                DEFAULT_SCHEMA,
+                serde_json::json!({ "properties": { "port": { "type": "integer" } } }),
+            ),
+            ("other", serde_json::json!({ "type": "object" })),
+        ]);
+        let backend = server.inner();
+
+        backend
+            .preload_schemas(&[DEFAULT_SCHEMA.to_string(), "other".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 2);
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        let diagnostics = backend
+            .compute_diagnostics(&uri, r#"{ "port": "80" }"#)
+            .await
+            .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        // validation used the compiled schema from the cache, nothing was resolved again
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 2);
+
+        assert!(matches!(
+            backend.preload_schemas(&["unknown".to_string()]).await,
+            Err(SchemaValidationError::SchemaNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_schemas_load_through_custom_resolver() {
+        let server = backend_with_schemas([("in-memory", serde_json::json!({ "type": "object" }))]);
+        let backend = server.inner();
+
+        let uri = Url::parse("file:///service.json").unwrap();
+        let schema = backend.get_or_load_schema(&uri, "in-memory").await.unwrap();
+        assert_eq!(*schema, serde_json::json!({ "type": "object" }));
+
+        // second load is served from the cache
+        backend.get_or_load_schema(&uri, "in-memory").await.unwrap();
+        assert_eq!(server.resolver.resolve_count.load(Ordering::SeqCst), 1);
+
+        assert!(matches!(
+            backend.get_or_load_schema(&uri, "unknown").await,
+            Err(SchemaValidationError::SchemaNotFound(_))
+        ));
+    }
+}
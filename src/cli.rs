@@ -0,0 +1,383 @@
+#[cfg(feature = "cli")]
+use std::collections::HashMap;
+#[cfg(feature = "cli")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::Serialize;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+#[cfg(feature = "cli")]
+use tower_lsp::lsp_types::{Position, Range};
+#[cfg(feature = "cli")]
+use tracing::warn;
+use tracing::{info, instrument};
+
+#[cfg(feature = "cli")]
+use crate::error::SchemaValidationError;
+use crate::error::ValidationResult;
+use crate::keywords::CustomKeywords;
+use crate::line_number;
+use crate::resolver::DefaultSchemaResolver;
+use crate::validation::CompiledSchema;
+#[cfg(feature = "cli")]
+use crate::validation::{ValidationFinding, ValidationKind};
+
+/// How `pur validate` prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// compiler style `file:line:col: severity: message`, one per line
+    #[default]
+    Compact,
+    /// a single JSON array of findings
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(OutputFormat::Compact),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!(
+                "unknown format '{}', expected compact or json",
+                other
+            )),
+        }
+    }
+}
+
+/// Arguments of `pur validate [--format=compact|json] [--schema=<id>] <file>...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+    pub format: OutputFormat,
+    pub schema: String,
+    pub files: Vec<PathBuf>,
+}
+
+impl CliArgs {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = CliArgs {
+            format: OutputFormat::default(),
+            schema: "service.schema".to_string(),
+            files: Vec::new(),
+        };
+
+        for arg in args {
+            if let Some(format) = arg.strip_prefix("--format=") {
+                parsed.format = format.parse()?;
+            } else if let Some(schema) = arg.strip_prefix("--schema=") {
+                parsed.schema = schema.to_string();
+            } else if arg.starts_with("--") {
+                return Err(format!("unknown option '{}'", arg));
+            } else {
+                parsed.files.push(PathBuf::from(arg));
+            }
+        }
+
+        if parsed.files.is_empty() {
+            return Err("no files to validate".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+/// A diagnostic flattened for command line output, lines and columns are 1-based
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Record {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub severity: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+}
+
+impl Record {
+    pub fn new(file: &str, diagnostic: &Diagnostic) -> Self {
+        // editors and compilers count from 1, LSP from 0
+        let (line, column) = line_number::to_one_based(diagnostic.range.start);
+        Record {
+            file: file.to_string(),
+            line,
+            column,
+            severity: severity_name(diagnostic.severity),
+            message: diagnostic.message.clone(),
+            pointer: diagnostic.source.clone(),
+        }
+    }
+
+    /// `file:line:col: severity: message`
+    pub fn to_compact(&self) -> String {
+        format!(
+            "{}:{}:{}: {}: {}",
+            self.file, self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+pub(crate) fn severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    }
+}
+
+/// Renders all records in the requested format
+pub fn render(records: &[Record], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Compact => records
+            .iter()
+            .map(Record::to_compact)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Json => {
+            serde_json::to_string_pretty(records).expect("records always serialize")
+        }
+    }
+}
+
+/// Validates every file and prints the results to stdout
+/// Returns whether all files were free of errors
+#[instrument(skip(args))]
+pub async fn run(args: &CliArgs) -> ValidationResult<bool> {
+    let resolver = DefaultSchemaResolver::default();
+    let schema =
+        CompiledSchema::load(&resolver, &args.schema, &CustomKeywords::default(), None).await?;
+
+    let mut records = Vec::new();
+    for file in &args.files {
+        let contents = tokio::fs::read_to_string(file).await?;
+        let diagnostics = schema.validate(&contents)?;
+        info!(file = %file.display(), count = diagnostics.len(), "Validated file");
+
+        let name = file.display().to_string();
+        records.extend(diagnostics.iter().map(|d| Record::new(&name, d)));
+    }
+
+    let output = render(&records, args.format);
+    if !output.is_empty() {
+        println!("{}", output);
+    }
+
+    Ok(records.iter().all(|r| r.severity != "error"))
+}
+
+/// Pattern matched against file paths relative to the validated directory, eg `**/*.service.json`
+#[cfg(feature = "cli")]
+pub type Glob = glob::Pattern;
+
+/// Identifier of a schema, resolved like `--schema`
+#[cfg(feature = "cli")]
+pub type SchemaId = String;
+
+/// Validates every file below `root` against the schema of the first glob matching it
+/// Files no glob matches are skipped, each schema is resolved and compiled once
+///
+/// A file that can't be read or isn't valid JSON, or whose schema fails to load, gets a
+/// single finding of kind `Other("file")` with the error and the walk continues
+#[cfg(feature = "cli")]
+#[instrument(skip(schema_map), fields(root = %root.display()))]
+pub async fn validate_dir(
+    root: &Path,
+    schema_map: &[(Glob, SchemaId)],
+) -> HashMap<PathBuf, Vec<ValidationFinding>> {
+    let resolver = DefaultSchemaResolver::default();
+    // a schema failing to load is not retried for every file it governs
+    let mut schemas: HashMap<&str, Result<CompiledSchema, String>> = HashMap::new();
+    let mut findings = HashMap::new();
+
+    for entry in walkdir::WalkDir::new(root).sort_by_file_name() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!(error = %e, "Failed to walk directory entry");
+                if let Some(path) = e.path() {
+                    findings.insert(path.to_path_buf(), vec![file_finding(e.to_string(), None)]);
+                }
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let Some((_, id)) = schema_map
+            .iter()
+            .find(|(glob, _)| glob.matches_path(relative))
+        else {
+            continue;
+        };
+
+        if !schemas.contains_key(id.as_str()) {
+            let schema = CompiledSchema::load(&resolver, id, &CustomKeywords::default(), None)
+                .await
+                .map_err(|e| format!("Schema {} failed to load: {}", id, e));
+            schemas.insert(id, schema);
+        }
+        let file_findings = match &schemas[id.as_str()] {
+            Ok(schema) => validate_file(schema, entry.path()).await,
+            Err(e) => vec![file_finding(e.clone(), None)],
+        };
+        info!(file = %relative.display(), count = file_findings.len(), "Validated file");
+
+        findings.insert(entry.into_path(), file_findings);
+    }
+
+    findings
+}
+
+/// Findings of the file at `path`, or the single finding of why it could not be validated
+#[cfg(feature = "cli")]
+async fn validate_file(schema: &CompiledSchema, path: &Path) -> Vec<ValidationFinding> {
+    let result = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => schema.findings(&contents),
+        Err(e) => Err(e.into()),
+    };
+
+    result.unwrap_or_else(|e| {
+        let position = match &e {
+            SchemaValidationError::JsonParseError(e) => Some(Position::new(
+                e.line().saturating_sub(1) as u32,
+                e.column().saturating_sub(1) as u32,
+            )),
+            _ => None,
+        };
+        vec![file_finding(e.to_string(), position)]
+    })
+}
+
+/// Finding for a whole file that could not be validated, at `position` or its start
+#[cfg(feature = "cli")]
+fn file_finding(message: String, position: Option<Position>) -> ValidationFinding {
+    let position = position.unwrap_or_default();
+    ValidationFinding {
+        instance_path: String::new(),
+        message,
+        range: Range::new(position, position),
+        kind: ValidationKind::Other("file".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "cli")]
+    use crate::test_util::TempDir;
+    use crate::validate_liberally;
+
+    #[test]
+    fn test_parse_args() {
+        let args = CliArgs::parse(["--format=json", "a.json", "b.json"].map(String::from)).unwrap();
+
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(
+            args.files,
+            vec![PathBuf::from("a.json"), PathBuf::from("b.json")]
+        );
+        assert!(CliArgs::parse(["--format=xml", "a.json"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_compact_output_for_known_bad_file() {
+        let file = "testing/test_pattern_mismatch.json";
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+        let contents = std::fs::read_to_string(file).unwrap();
+
+        let diagnostics = validate_liberally(&schema, &contents).unwrap();
+        let records: Vec<Record> = diagnostics.iter().map(|d| Record::new(file, d)).collect();
+
+        assert_eq!(
+            render(&records, OutputFormat::Compact),
+            r#"testing/test_pattern_mismatch.json:3:1: error: Path /version, Error: "v1" does not match "^[0-9]+\.[0-9]+\.[0-9]+$""#
+        );
+    }
+
+    #[test]
+    fn test_output_is_one_based_while_lsp_stays_zero_based() {
+        let file = "testing/test_pattern_mismatch.json";
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+        let contents = std::fs::read_to_string(file).unwrap();
+
+        let diagnostic = validate_liberally(&schema, &contents).unwrap().remove(0);
+        let record = Record::new(file, &diagnostic);
+        let instance: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        let finding = crate::validation::SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap()
+            .remove(0);
+
+        // `"version"` is on the third line
+        assert_eq!(record.line, 3);
+        assert_eq!(diagnostic.range.start.line, 2);
+        assert_eq!(finding.to_one_based(), (record.line, record.column));
+    }
+
+    #[cfg(feature = "cli")]
+    #[tokio::test]
+    async fn test_validate_dir_matches_files_to_schemas() {
+        let root = TempDir::new("validate-dir");
+        std::fs::create_dir_all(root.join("services")).unwrap();
+        std::fs::create_dir_all(root.join("schemas")).unwrap();
+
+        let labels_schema = root.join("schemas/labels.schema.json");
+        std::fs::write(
+            &labels_schema,
+            r#"{ "additionalProperties": { "type": "string" } }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("services/api.service.json"),
+            include_str!("../testing/test_pattern_mismatch.json"),
+        )
+        .unwrap();
+        std::fs::write(root.join("services/api.labels.json"), r#"{ "team": 1 }"#).unwrap();
+        std::fs::write(root.join("services/broken.service.json"), "{ \"service\": ").unwrap();
+        std::fs::write(root.join("services/api.missing.json"), "{}").unwrap();
+        std::fs::write(root.join("README.md"), "not validated").unwrap();
+
+        let schema_map = [
+            (
+                Glob::new("**/*.service.json").unwrap(),
+                "service.schema".to_string(),
+            ),
+            (
+                Glob::new("**/*.labels.json").unwrap(),
+                labels_schema.display().to_string(),
+            ),
+            (
+                Glob::new("**/*.missing.json").unwrap(),
+                "missing.schema.json".to_string(),
+            ),
+        ];
+        let findings = validate_dir(&root, &schema_map).await;
+
+        // README.md matches no glob and is skipped, files that fail are still reported
+        assert_eq!(findings.len(), 4);
+        assert!(findings.values().all(|found| found.len() == 1));
+        let broken = &findings[&root.join("services/broken.service.json")][0];
+        assert_eq!(broken.kind, ValidationKind::Other("file".to_string()));
+        assert!(broken.message.contains("parse JSON"), "{}", broken.message);
+        assert_eq!(broken.range.start.line, 0);
+        let missing = &findings[&root.join("services/api.missing.json")][0];
+        assert!(
+            missing.message.contains("missing.schema.json"),
+            "{}",
+            missing.message
+        );
+        assert_eq!(
+            findings[&root.join("services/api.labels.json")][0].instance_path,
+            "/team"
+        );
+        assert_eq!(
+            findings[&root.join("services/api.service.json")][0].instance_path,
+            "/version"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+
+use jsonschema::JsonType;
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use serde_json::Value;
+use tower_lsp::lsp_types::{Diagnostic, Range, TextEdit};
+use tracing::{debug, instrument, trace};
+
+use crate::document::Document;
+use crate::error::ValidationResult;
+use crate::line_number::PositionEncoding;
+use crate::value_span::{self, Span};
+use crate::{cursor, introspection, json_pointer, validation};
+
+/// A safe, unambiguous edit resolving a single problem in the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub title: String,
+    /// byte span of the raw file contents replaced by `new_text`
+    pub span: Span,
+    pub new_text: String,
+}
+
+impl Fix {
+    /// LSP range of the replaced span in `document`, the text the fix was computed on
+    pub fn range(&self, document: &Document) -> Range {
+        Range {
+            start: document.to_position(self.span.0, PositionEncoding::Utf16),
+            end: document.to_position(self.span.1, PositionEncoding::Utf16),
+        }
+    }
+
+    pub fn to_text_edit(&self, document: &Document) -> TextEdit {
+        TextEdit {
+            range: self.range(document),
+            new_text: self.new_text.clone(),
+        }
+    }
+}
+
+/// Gathers every fix that can be applied without asking the user:
+/// - missing required properties whose schema declares a `default`
+/// - values violating `const`, replaced by the expected constant
+/// - duplicate keys, keeping the last occurrence (the one serde_json keeps)
+/// - numbers written as strings (or the reverse) where the schema wants the other type
+#[instrument(skip_all)]
+pub fn safe_fixes(
+    json_schema: &Value,
+    file_as_json: &Value,
+    raw_file_contents: &str,
+) -> ValidationResult<Vec<Fix>> {
+    let validator = validation::compile(json_schema)?;
+
+    let mut fixes = duplicate_key_fixes(raw_file_contents);
+    // missing properties per object, all inserted by one fix at the same point
+    let mut missing: Vec<(String, Vec<String>)> = Vec::new();
+
+    for error in validator.iter_errors(file_as_json) {
+        let pointer = validation::canonical_pointer(error.instance_path());
+        let pointer = pointer.as_str();
+        let fix = match error.kind() {
+            ValidationErrorKind::Required { property } => {
+                if let Some(name) = property.as_str() {
+                    match missing.iter_mut().find(|(p, _)| p == pointer) {
+                        Some((_, names)) => names.push(name.to_string()),
+                        None => missing.push((pointer.to_string(), vec![name.to_string()])),
+                    }
+                }
+                None
+            }
+            ValidationErrorKind::Constant { expected_value } => {
+                value_span::locate(pointer, raw_file_contents).map(|span| Fix {
+                    title: format!("Replace with expected constant {}", expected_value),
+                    span,
+                    new_text: expected_value.to_string(),
+                })
+            }
+            ValidationErrorKind::Type { kind } => {
+                coercion_fix(kind, error.instance(), pointer, raw_file_contents)
+            }
+            _ => None,
+        };
+
+        if let Some(fix) = fix {
+            trace!(title = %fix.title, "Found safe fix");
+            fixes.push(fix);
+        }
+    }
+
+    fixes.extend(missing.iter().filter_map(|(pointer, names)| {
+        missing_defaults_fix(json_schema, pointer, names, raw_file_contents)
+    }));
+
+    debug!(fix_count = fixes.len(), "Collected safe fixes");
+    Ok(fixes)
+}
+
+/// Fix-it replacing the value of a diagnostic carrying a `suggestion` in its data
+/// The diagnostic source holds the instance pointer of the offending value
+pub fn suggestion_fix(diagnostic: &Diagnostic, raw_file_contents: &str) -> Option<Fix> {
+    let suggestion = diagnostic.data.as_ref()?.get("suggestion")?;
+    let span = value_span::locate(diagnostic.source.as_deref()?, raw_file_contents)?;
+
+    Some(Fix {
+        title: format!("Replace with {}", suggestion),
+        span,
+        new_text: suggestion.to_string(),
+    })
+}
+
+/// Applies fixes back to front so earlier spans stay valid
+/// Fixes overlapping an already applied one are skipped
+pub fn apply(raw_file_contents: &str, fixes: &[Fix]) -> String {
+    let mut fixed = raw_file_contents.to_owned();
+    for fix in non_overlapping(fixes) {
+        fixed.replace_range(fix.span.0..fix.span.1, &fix.new_text);
+    }
+    fixed
+}
+
+/// The fixes that can apply together, last in the document first
+/// A fix overlapping one further down the document is dropped, as is a second insert at
+/// the same point: their order would be undefined
+pub fn non_overlapping(fixes: &[Fix]) -> Vec<&Fix> {
+    let mut ordered: Vec<&Fix> = fixes.iter().collect();
+    ordered.sort_by_key(|fix| std::cmp::Reverse(fix.span));
+
+    let mut limit = usize::MAX;
+    let mut insert_at = None;
+    ordered.retain(|fix| {
+        let is_insert = fix.span.0 == fix.span.1;
+        if fix.span.1 > limit || (is_insert && insert_at == Some(fix.span.0)) {
+            debug!(title = %fix.title, "Skipping overlapping fix");
+            return false;
+        }
+        limit = fix.span.0;
+        if is_insert {
+            insert_at = Some(fix.span.0);
+        }
+        true
+    });
+    ordered
+}
+
+/// Inserts `"name": <default>` for every one of `names` the schema has a default for
+/// into the object at `pointer`, as a single comma separated insert
+fn missing_defaults_fix(
+    json_schema: &Value,
+    pointer: &str,
+    names: &[String],
+    raw_file_contents: &str,
+) -> Option<Fix> {
+    let (names, entries): (Vec<&str>, Vec<String>) = names
+        .iter()
+        .filter_map(|name| {
+            let property_pointer = format!("{}/{}", pointer, cursor::escape(name));
+            let default = introspection::resolve(json_schema, &property_pointer)?.get("default")?;
+            Some((
+                name.as_str(),
+                format!("{}: {}", Value::from(name.as_str()), default),
+            ))
+        })
+        .unzip();
+    if entries.is_empty() {
+        return None;
+    }
+
+    let (index, needs_leading_comma) =
+        json_pointer::object_insertion_index(pointer, raw_file_contents)?;
+    let (object_start, _) = value_span::locate(pointer, raw_file_contents)?;
+    let members = value_span::members(raw_file_contents, object_start)?;
+
+    let separator = match members.last() {
+        // an empty object gets the entries right inside its braces
+        None => None,
+        Some(last) => {
+            let line_start = raw_file_contents[..last.key_span.0]
+                .rfind('\n')
+                .map_or(0, |idx| idx + 1);
+            Some(if line_start > object_start {
+                // multi line object, match the indentation of the last member
+                let indent = &raw_file_contents[line_start..last.key_span.0];
+                format!("\n{}", indent)
+            } else {
+                " ".to_string()
+            })
+        }
+    };
+    let new_text = match separator {
+        None => entries.join(", "),
+        Some(separator) => format!(
+            "{}{}{}",
+            if needs_leading_comma { "," } else { "" },
+            separator,
+            entries.join(&format!(",{}", separator))
+        ),
+    };
+
+    let title = match names.as_slice() {
+        [name] => format!("Insert missing property \"{}\" with its default", name),
+        _ => format!(
+            "Insert missing properties {} with their defaults",
+            names
+                .iter()
+                .map(|name| format!("\"{}\"", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    Some(Fix {
+        title,
+        span: (index, index),
+        new_text,
+    })
+}
+
+/// Adds or strips the quotes around a value whose text already is of the expected type,
+/// eg `"8080"` for an integer; strings only coerce when they parse as the wanted number
+fn coercion_fix(
+    kind: &TypeKind,
+    instance: &Value,
+    pointer: &str,
+    raw_file_contents: &str,
+) -> Option<Fix> {
+    let accepts = |ty: JsonType| match kind {
+        TypeKind::Single(expected) => *expected == ty,
+        TypeKind::Multiple(types) => types.contains(ty),
+    };
+
+    let (title, new_text) = match instance {
+        Value::String(text) => {
+            let number: serde_json::Number = text.trim().parse().ok()?;
+            let fits = if number.is_f64() {
+                accepts(JsonType::Number)
+            } else {
+                accepts(JsonType::Integer) || accepts(JsonType::Number)
+            };
+            if !fits {
+                return None;
+            }
+            ("Convert string to number".to_string(), number.to_string())
+        }
+        Value::Number(number) if accepts(JsonType::String) => (
+            "Convert number to string".to_string(),
+            Value::from(number.to_string()).to_string(),
+        ),
+        _ => return None,
+    };
+
+    let span = value_span::locate(pointer, raw_file_contents)?;
+    Some(Fix {
+        title,
+        span,
+        new_text,
+    })
+}
+
+/// Removes every occurrence of a duplicated key except the last, in all objects
+fn duplicate_key_fixes(raw_file_contents: &str) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    let start = value_span::skip_whitespace(raw_file_contents, 0);
+    collect_duplicates(raw_file_contents, start, &mut fixes);
+    fixes
+}
+
+fn collect_duplicates(raw_file_contents: &str, start: usize, fixes: &mut Vec<Fix>) {
+    match raw_file_contents.as_bytes().get(start) {
+        Some(b'{') => {
+            let Some(members) = value_span::members(raw_file_contents, start) else {
+                return;
+            };
+
+            let mut last_index: HashMap<&str, usize> = HashMap::new();
+            for (idx, member) in members.iter().enumerate() {
+                last_index.insert(&member.key, idx);
+            }
+
+            for (idx, member) in members.iter().enumerate() {
+                if last_index[member.key.as_str()] != idx {
+                    // an earlier duplicate always has a following member to cut up to
+                    fixes.push(Fix {
+                        title: format!("Remove duplicate key \"{}\"", member.key),
+                        span: (member.key_span.0, members[idx + 1].key_span.0),
+                        new_text: String::new(),
+                    });
+                } else {
+                    collect_duplicates(raw_file_contents, member.value_span.0, fixes);
+                }
+            }
+        }
+        Some(b'[') => {
+            for (element_start, _) in
+                value_span::elements(raw_file_contents, start).unwrap_or_default()
+            {
+                collect_duplicates(raw_file_contents, element_start, fixes);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_keys_keep_last_occurrence() {
+        let contents = r#"{ "mode": "dev", "port": 1, "mode": "prod" }"#;
+        let json: Value = serde_json::from_str(contents).unwrap();
+
+        let fixes = safe_fixes(&serde_json::json!({}), &json, contents).unwrap();
+
+        assert_eq!(fixes.len(), 1);
+        assert_eq!(apply(contents, &fixes), r#"{ "port": 1, "mode": "prod" }"#);
+    }
+
+    #[test]
+    fn test_suggestion_replaces_offending_value() {
+        let contents = r#"{ "ports": [ { "protocol": "TCP" } ] }"#;
+        let diagnostic = Diagnostic {
+            source: Some("/ports/0/protocol".to_string()),
+            data: Some(serde_json::json!({ "suggestion": "tcp" })),
+            ..Default::default()
+        };
+
+        let fix = suggestion_fix(&diagnostic, contents).expect("suggestion fix");
+
+        assert_eq!(
+            apply(contents, &[fix]),
+            r#"{ "ports": [ { "protocol": "tcp" } ] }"#
+        );
+    }
+
+    #[test]
+    fn test_quoted_number_coerces_to_integer() {
+        let schema = serde_json::json!({
+            "properties": {
+                "containerPort": { "type": "integer" },
+                "name": { "type": "string" },
+                "image": { "type": "integer" }
+            }
+        });
+        let contents = r#"{ "containerPort": "8080", "name": 80, "image": "nginx" }"#;
+        let json: Value = serde_json::from_str(contents).unwrap();
+
+        let fixes = safe_fixes(&schema, &json, contents).unwrap();
+
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(
+            apply(contents, &fixes),
+            r#"{ "containerPort": 8080, "name": "80", "image": "nginx" }"#
+        );
+    }
+
+    #[test]
+    fn test_defaults_for_empty_object_are_comma_separated() {
+        let schema = serde_json::json!({
+            "required": ["a", "b"],
+            "properties": {
+                "a": { "default": 1 },
+                "b": { "default": 2 }
+            }
+        });
+        let contents = "{}";
+        let json: Value = serde_json::from_str(contents).unwrap();
+
+        let fixes = safe_fixes(&schema, &json, contents).unwrap();
+        let fixed = apply(contents, &fixes);
+
+        assert_eq!(fixed, r#"{"a": 1, "b": 2}"#);
+        assert!(serde_json::from_str::<Value>(&fixed).is_ok());
+
+        // separate inserts at one point have no order, only one of them applies
+        let insert = |new_text: &str| Fix {
+            title: String::new(),
+            span: (1, 1),
+            new_text: new_text.to_string(),
+        };
+        assert_eq!(non_overlapping(&[insert("a"), insert("b")]).len(), 1);
+    }
+
+    #[test]
+    fn test_const_violation_is_replaced() {
+        let schema = serde_json::json!({
+            "properties": { "kind": { "const": "service" } }
+        });
+        let contents = r#"{ "kind": "servce" }"#;
+        let json: Value = serde_json::from_str(contents).unwrap();
+
+        let fixes = safe_fixes(&schema, &json, contents).unwrap();
+
+        assert_eq!(apply(contents, &fixes), r#"{ "kind": "service" }"#);
+    }
+}
@@ -0,0 +1,228 @@
+use serde_json::Value;
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat};
+use tracing::{instrument, trace};
+
+use crate::introspection;
+
+/// Completion items for the keys of the object described by `schema_node`
+///
+/// Object-valued properties with required sub-properties insert a snippet stub with
+/// placeholders for each required key, everything else inserts `"key": `
+#[instrument(skip(schema_node))]
+pub fn property_completions(schema_node: &Value) -> Vec<CompletionItem> {
+    let Some(properties) = schema_node.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let detail = property
+                .get("type")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+
+            match object_snippet(name, property) {
+                Some(snippet) => {
+                    trace!(property = %name, "Created snippet completion");
+                    CompletionItem {
+                        label: name.clone(),
+                        kind: Some(CompletionItemKind::PROPERTY),
+                        detail,
+                        insert_text: Some(snippet),
+                        insert_text_format: Some(InsertTextFormat::SNIPPET),
+                        ..Default::default()
+                    }
+                }
+                None => CompletionItem {
+                    label: name.clone(),
+                    kind: Some(CompletionItemKind::PROPERTY),
+                    detail,
+                    insert_text: Some(format!("{}: ", Value::from(name.as_str()))),
+                    insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+                    ..Default::default()
+                },
+            }
+        })
+        .collect()
+}
+
+/// Completion items for a value position described by `schema_node`
+/// Offers the schema `default` first and preselected, followed by the schema `examples`
+#[instrument(skip(schema_node))]
+pub fn value_completions(schema_node: &Value) -> Vec<CompletionItem> {
+    let default = schema_node.get("default");
+
+    let default_item = default.map(|default| CompletionItem {
+        label: default.to_string(),
+        kind: Some(CompletionItemKind::VALUE),
+        detail: Some("default".to_string()),
+        insert_text: Some(default.to_string()),
+        preselect: Some(true),
+        sort_text: Some("0".to_string()),
+        ..Default::default()
+    });
+
+    let examples = schema_node
+        .get("examples")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        // the default is already offered
+        .filter(|example| Some(*example) != default)
+        .map(|example| CompletionItem {
+            label: example.to_string(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail: Some("example".to_string()),
+            insert_text: Some(example.to_string()),
+            sort_text: Some("1".to_string()),
+            ..Default::default()
+        });
+
+    default_item.into_iter().chain(examples).collect()
+}
+
+/// Builds `"name": { "required": $1, ... }` for object properties with required keys
+fn object_snippet(name: &str, property: &Value) -> Option<String> {
+    let required = property.get("required").and_then(Value::as_array)?;
+    if required.is_empty() {
+        return None;
+    }
+
+    let stubs: Vec<String> = required
+        .iter()
+        .filter_map(Value::as_str)
+        .enumerate()
+        .map(|(idx, key)| {
+            let sub_schema = introspection::resolve(property, &format!("/{}", key));
+            format!(
+                "{}: {}",
+                snippet_string(key),
+                placeholder(idx + 1, sub_schema)
+            )
+        })
+        .collect();
+
+    Some(format!(
+        "{}: {{ {} }}",
+        snippet_string(name),
+        stubs.join(", ")
+    ))
+}
+
+/// `text` from the schema as a JSON string literal the snippet inserts as is, eg a key
+/// `${HOME}` is not read as a snippet variable
+fn snippet_string(text: &str) -> String {
+    let literal = Value::from(text).to_string();
+    let mut escaped = String::with_capacity(literal.len());
+    for ch in literal.chars() {
+        if matches!(ch, '\\' | '$' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Snippet placeholder shaped after the expected type of the value
+fn placeholder(tab_stop: usize, sub_schema: Option<&Value>) -> String {
+    match sub_schema
+        .and_then(|s| s.get("type"))
+        .and_then(Value::as_str)
+    {
+        Some("string") => format!("\"${}\"", tab_stop),
+        Some("object") => format!("{{ ${} }}", tab_stop),
+        Some("array") => format!("[ ${} ]", tab_stop),
+        _ => format!("${}", tab_stop),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_property_completes_to_snippet() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+
+        let items = property_completions(&schema);
+        let runtime = items
+            .iter()
+            .find(|item| item.label == "runtime")
+            .expect("runtime completion");
+
+        assert_eq!(runtime.insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(
+            runtime.insert_text.as_deref(),
+            Some(r#""runtime": { "type": "$1" }"#)
+        );
+    }
+
+    #[test]
+    fn test_snippet_escapes_schema_text() {
+        let schema = serde_json::json!({
+            "properties": {
+                "${HOME}": { "type": "object", "required": ["a$b", "c}\\d"] }
+            }
+        });
+
+        let items = property_completions(&schema);
+
+        assert_eq!(
+            items[0].insert_text.as_deref(),
+            Some(r#""\${HOME\}": { "a\$b": $1, "c\}\\\\d": $2 }"#)
+        );
+    }
+
+    #[test]
+    fn test_examples_offered_for_values() {
+        let node = serde_json::json!({ "type": "string", "examples": ["nginx", "redis"] });
+
+        let items = value_completions(&node);
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+
+        assert_eq!(labels, vec![r#""nginx""#, r#""redis""#]);
+        assert_eq!(items[0].detail.as_deref(), Some("example"));
+    }
+
+    #[test]
+    fn test_default_offered_first_and_preselected() {
+        let node = serde_json::json!({
+            "type": "string",
+            "default": "redis",
+            "examples": ["nginx", "redis"]
+        });
+
+        let items = value_completions(&node);
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+
+        assert_eq!(labels, vec![r#""redis""#, r#""nginx""#]);
+        assert_eq!(items[0].preselect, Some(true));
+        assert_eq!(items[0].detail.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_scalar_property_completes_to_key() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+
+        let items = property_completions(&schema);
+        let service = items
+            .iter()
+            .find(|item| item.label == "service")
+            .expect("service completion");
+
+        assert_eq!(
+            service.insert_text_format,
+            Some(InsertTextFormat::PLAIN_TEXT)
+        );
+        assert_eq!(service.insert_text.as_deref(), Some(r#""service": "#));
+
+        let quoted = serde_json::json!({ "properties": { "say \"hi\"": { "type": "string" } } });
+        assert_eq!(
+            property_completions(&quoted)[0].insert_text.as_deref(),
+            Some(r#""say \"hi\"": "#)
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// JSON Schema draft forced over the one a schema declares (or the default)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum SchemaDraft {
+    #[serde(rename = "draft4")]
+    Draft4,
+    #[serde(rename = "draft6")]
+    Draft6,
+    #[serde(rename = "draft7")]
+    Draft7,
+    #[serde(rename = "2019-09")]
+    Draft201909,
+    #[serde(rename = "2020-12")]
+    Draft202012,
+}
+
+impl SchemaDraft {
+    pub fn to_jsonschema(self) -> jsonschema::Draft {
+        match self {
+            SchemaDraft::Draft4 => jsonschema::Draft::Draft4,
+            SchemaDraft::Draft6 => jsonschema::Draft::Draft6,
+            SchemaDraft::Draft7 => jsonschema::Draft::Draft7,
+            SchemaDraft::Draft201909 => jsonschema::Draft::Draft201909,
+            SchemaDraft::Draft202012 => jsonschema::Draft::Draft202012,
+        }
+    }
+}
+
+/// Severity errors are reported with in documents matching `glob`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeverityOverride {
+    /// Matched against the document's path, both absolute and relative to its workspace folder
+    pub glob: PathGlob,
+    pub severity: OverrideSeverity,
+}
+
+/// Path glob compiled once when the config is read
+/// `**` spans any number of directories, `*` and `?` stay within one path segment and
+/// `[...]` is a character class. An invalid glob matches nothing
+#[derive(Debug, Clone)]
+pub struct PathGlob {
+    glob: String,
+    regex: Option<Regex>,
+}
+
+impl PathGlob {
+    pub fn new(glob: &str) -> Self {
+        Self {
+            glob: glob.to_string(),
+            regex: glob_regex(glob).and_then(|pattern| Regex::new(&pattern).ok()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.glob
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        self.regex
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(&path.to_string_lossy()))
+    }
+}
+
+impl<'de> Deserialize<'de> for PathGlob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Anchored regex matching the same paths as `glob`, None for an unclosed `[`
+fn glob_regex(glob: &str) -> Option<String> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.next_if_eq(&'/').is_some() {
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => {
+                pattern.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    pattern.push('^');
+                }
+                loop {
+                    match chars.next()? {
+                        ']' => break,
+                        '\\' => pattern.push_str("\\\\"),
+                        ch => pattern.push(ch),
+                    }
+                }
+                pattern.push(']');
+            }
+            ch => pattern.push_str(&regex::escape(ch.encode_utf8(&mut [0; 4]))),
+        }
+    }
+
+    pattern.push('$');
+    Some(pattern)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl OverrideSeverity {
+    pub fn to_lsp(self) -> DiagnosticSeverity {
+        match self {
+            OverrideSeverity::Error => DiagnosticSeverity::ERROR,
+            OverrideSeverity::Warning => DiagnosticSeverity::WARNING,
+            OverrideSeverity::Information => DiagnosticSeverity::INFORMATION,
+            OverrideSeverity::Hint => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+/// Validation and server options
+/// Read from the client's `initializationOptions`, unknown or missing fields use defaults
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    /// Report string enum values that only differ in case as warnings with a fix-it
+    pub case_insensitive_enums: bool,
+    /// Warn at the document root when no `$schema` field (or shebang) is declared
+    pub require_schema_field: bool,
+    /// Reject documents whose root is an array or scalar before validating against the schema
+    pub require_object_root: bool,
+    /// Link violations nested under another violation back to it as related information
+    pub related_information: bool,
+    /// When more than this many elements of one array fail the same schema keyword,
+    /// report a single diagnostic over the array with the count instead
+    pub collapse_array_errors: Option<usize>,
+    /// Allow keys the schema does not declare at the document root, nested objects stay strict
+    pub allow_unknown_top_level: bool,
+    /// Treat string values that are a single `${ENV_VAR}` placeholder as wildcards,
+    /// skipping their `pattern`, `format` and `enum` checks so templates validate
+    pub env_placeholders: bool,
+    /// How many fix-and-reparse cycles run on a syntax error to report the errors after it,
+    /// 10 by default, 0 reports only the first one
+    pub max_parse_recovery_iterations: usize,
+    /// Prefix every message with the id of the schema that produced it and record it
+    /// as `data.schema`, for setups validating against more than one schema
+    pub schema_id_in_diagnostics: bool,
+    /// Warn on `format` names no validator exists for, in schema documents being edited
+    /// and at the start of documents validated against a schema using them
+    pub warn_unknown_formats: bool,
+    /// Warn on local `$ref`s pointing at no node, in schema documents being edited
+    pub warn_dangling_refs: bool,
+    /// Root key holding a schema embedded in the document, eg `__schema`; when present the
+    /// rest of the document is validated against it instead of the configured schema
+    pub embedded_schema_key: Option<String>,
+    /// Publish one information diagnostic confirming a document is valid instead of nothing
+    pub report_valid_documents: bool,
+    /// Documents larger than this many bytes are not validated, they get a single warning
+    pub max_document_bytes: Option<usize>,
+    /// Normalize schema identifiers before caching them, so eg `https://x/s#` and
+    /// `https://X/s` load and compile once
+    pub normalize_schema_ids: bool,
+    /// Documentation site of the schemas; set, every violation links to its failing schema
+    /// node as `code_description`, the schema path appended as fragment
+    pub docs_base_url: Option<String>,
+    /// Compile every schema as this draft, eg `draft7`, instead of detecting it
+    pub draft: Option<SchemaDraft>,
+    /// Report errors in documents matching a glob with another severity, eg warnings for
+    /// scratch files under `drafts/**`; syntax and schema errors alike, the first match wins
+    pub severity_overrides: Vec<SeverityOverride>,
+    /// Directory schemas are read from, relative paths resolve against the document's
+    /// workspace folder so every folder can keep its own schemas
+    pub schema_dir: Option<PathBuf>,
+    /// Zip archive schemas are loaded from instead of the defaults (needs the `zip` feature)
+    pub schema_bundle: Option<PathBuf>,
+    /// Directory the last fetched copy of every schema is kept in, served with a notice
+    /// when fetching it fails, eg offline
+    pub schema_cache_dir: Option<PathBuf>,
+    /// Schema id per language id, eg `{"jsonc": "tsconfig.schema"}`, for documents that do
+    /// not declare their own `$schema`
+    pub language_schemas: HashMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            case_insensitive_enums: Default::default(),
+            require_schema_field: Default::default(),
+            require_object_root: Default::default(),
+            related_information: Default::default(),
+            collapse_array_errors: Default::default(),
+            allow_unknown_top_level: Default::default(),
+            env_placeholders: Default::default(),
+            max_parse_recovery_iterations: 10,
+            schema_id_in_diagnostics: Default::default(),
+            warn_unknown_formats: Default::default(),
+            warn_dangling_refs: Default::default(),
+            embedded_schema_key: Default::default(),
+            report_valid_documents: Default::default(),
+            max_document_bytes: Default::default(),
+            normalize_schema_ids: Default::default(),
+            docs_base_url: Default::default(),
+            draft: Default::default(),
+            severity_overrides: Default::default(),
+            schema_dir: Default::default(),
+            schema_bundle: Default::default(),
+            schema_cache_dir: Default::default(),
+            language_schemas: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Shared default config for callers that do not configure anything
+    pub fn default_ref() -> &'static Config {
+        static DEFAULT: OnceLock<Config> = OnceLock::new();
+        DEFAULT.get_or_init(Config::default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_globs() {
+        let drafts = PathGlob::new("drafts/**");
+        assert!(drafts.matches(Path::new("drafts/scratch.json")));
+        assert!(drafts.matches(Path::new("drafts/a/b.json")));
+        assert!(!drafts.matches(Path::new("src/drafts/scratch.json")));
+
+        let nested = PathGlob::new("**/*.draft.json");
+        assert!(nested.matches(Path::new("service.draft.json")));
+        assert!(nested.matches(Path::new("/work/api/service.draft.json")));
+        assert!(!nested.matches(Path::new("service.json")));
+
+        let single = PathGlob::new("[!._]?.json");
+        assert!(single.matches(Path::new("ab.json")));
+        assert!(!single.matches(Path::new("_b.json")));
+        assert!(!single.matches(Path::new("a/b.json")));
+
+        assert!(!PathGlob::new("drafts/[").matches(Path::new("drafts/[")));
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let config: Config = serde_json::from_str(r#"{ "requireSchemaField": true }"#).unwrap();
+
+        assert!(config.require_schema_field);
+        assert_eq!(config.max_parse_recovery_iterations, 10);
+        assert_eq!(Config::default().max_parse_recovery_iterations, 10);
+    }
+}
@@ -0,0 +1,266 @@
+use tower_lsp::lsp_types::{Position, Range};
+use tracing::{instrument, trace};
+
+use crate::{line_number, value_span};
+
+/// Whether the cursor sits where an object key or a value is expected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CursorLocation {
+    Key,
+    Value,
+}
+
+/// Json Pointer context at a cursor position
+/// For `Key` the pointer is the enclosing object, for `Value` it is the value itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorContext {
+    pub pointer: String,
+    pub location: CursorLocation,
+}
+
+enum Frame {
+    Object {
+        pointer: String,
+        key: Option<String>,
+        expecting_value: bool,
+    },
+    Array {
+        pointer: String,
+        index: usize,
+    },
+}
+
+impl Frame {
+    /// Pointer of the value currently being written inside this container
+    fn value_pointer(&self) -> String {
+        match self {
+            Frame::Object { pointer, key, .. } => {
+                format!("{}/{}", pointer, escape(key.as_deref().unwrap_or_default()))
+            }
+            Frame::Array { pointer, index } => format!("{}/{}", pointer, index),
+        }
+    }
+}
+
+/// Resolves the Json Pointer context at `position`
+/// Scans the text before the cursor only, so documents being edited (unterminated,
+/// missing values) still produce a context
+#[instrument(skip(file_contents), fields(line = position.line, character = position.character))]
+pub fn context_at(file_contents: &str, position: Position) -> Option<CursorContext> {
+    let index = line_number::to_index(file_contents, position.line, position.character)?;
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut string_buffer = String::new();
+
+    for ch in file_contents[..index].chars() {
+        if in_string {
+            match ch {
+                _ if escaped => {
+                    escaped = false;
+                    string_buffer.push(ch);
+                }
+                '\\' => escaped = true,
+                '"' => {
+                    in_string = false;
+                    if let Some(Frame::Object {
+                        key,
+                        expecting_value: false,
+                        ..
+                    }) = stack.last_mut()
+                    {
+                        *key = Some(std::mem::take(&mut string_buffer));
+                    }
+                }
+                _ => string_buffer.push(ch),
+            }
+            continue;
+        }
+
+        match ch {
+            '"' => {
+                in_string = true;
+                string_buffer.clear();
+            }
+            '{' => {
+                let pointer = stack.last().map(Frame::value_pointer).unwrap_or_default();
+                stack.push(Frame::Object {
+                    pointer,
+                    key: None,
+                    expecting_value: false,
+                });
+            }
+            '[' => {
+                let pointer = stack.last().map(Frame::value_pointer).unwrap_or_default();
+                stack.push(Frame::Array { pointer, index: 0 });
+            }
+            '}' | ']' => {
+                stack.pop();
+            }
+            ':' => {
+                if let Some(Frame::Object {
+                    expecting_value, ..
+                }) = stack.last_mut()
+                {
+                    *expecting_value = true;
+                }
+            }
+            ',' => match stack.last_mut() {
+                Some(Frame::Object {
+                    key,
+                    expecting_value,
+                    ..
+                }) => {
+                    *key = None;
+                    *expecting_value = false;
+                }
+                Some(Frame::Array { index, .. }) => *index += 1,
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    let context = match stack.last() {
+        Some(Frame::Object {
+            pointer,
+            expecting_value: false,
+            ..
+        }) => CursorContext {
+            pointer: pointer.clone(),
+            location: CursorLocation::Key,
+        },
+        Some(frame) => CursorContext {
+            pointer: frame.value_pointer(),
+            location: CursorLocation::Value,
+        },
+        None => CursorContext {
+            pointer: String::new(),
+            location: CursorLocation::Value,
+        },
+    };
+
+    trace!(pointer = %context.pointer, location = ?context.location, "Resolved cursor context");
+
+    Some(context)
+}
+
+/// Json Pointer of the innermost key or value under `position` in a well formed document
+/// Hovering a key yields the pointer of the value it names
+#[instrument(skip(file_contents), fields(line = position.line, character = position.character))]
+pub fn pointer_at(file_contents: &str, position: Position) -> Option<String> {
+    let index = line_number::to_index(file_contents, position.line, position.character)?;
+    let start = value_span::skip_whitespace(file_contents, 0);
+    let end = value_span::value_end(file_contents, start)?;
+    if !(start..end).contains(&index) {
+        return None;
+    }
+
+    let mut pointer = String::new();
+    let mut container_start = start;
+    loop {
+        let next = match file_contents.as_bytes()[container_start] {
+            b'{' => value_span::members(file_contents, container_start)?
+                .into_iter()
+                .find(|m| (m.key_span.0..m.value_span.1).contains(&index))
+                .map(|m| (escape(&m.key), m.value_span.0)),
+            b'[' => value_span::elements(file_contents, container_start)?
+                .into_iter()
+                .enumerate()
+                .find(|(_, (s, e))| (*s..*e).contains(&index))
+                .map(|(idx, (s, _))| (idx.to_string(), s)),
+            _ => None,
+        };
+
+        let Some((segment, value_start)) = next else {
+            break;
+        };
+        pointer.push('/');
+        pointer.push_str(&segment);
+        container_start = value_start;
+    }
+
+    trace!(pointer = %pointer, "Resolved pointer under cursor");
+    Some(pointer)
+}
+
+/// Json Pointer of the innermost value enclosing all of `range`, eg a selection
+/// An empty range behaves like `pointer_at` its start
+pub fn pointer_enclosing(file_contents: &str, range: Range) -> Option<String> {
+    let start = pointer_at(file_contents, range.start)?;
+    if range.end <= range.start {
+        return Some(start);
+    }
+
+    // the end is exclusive, its last selected character decides what it is in
+    let end_index = line_number::to_index(file_contents, range.end.line, range.end.character)?;
+    let last = file_contents[..end_index]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(idx, _)| idx);
+    let end = pointer_at(file_contents, line_number::to_position(file_contents, last))?;
+
+    let common: Vec<&str> = start
+        .split('/')
+        .zip(end.split('/'))
+        .take_while(|(a, b)| a == b)
+        .map(|(segment, _)| segment)
+        .collect();
+    Some(common.join("/"))
+}
+
+/// Escapes a key into a single RFC 6901 pointer segment
+pub(crate) fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"{
+  "service": "api",
+  "runtime": {
+    "type": "docker",
+    
+  },
+  "ports": [
+    { "containerPort": 8080 },
+    { "protocol": "tcp" }
+  ]
+}"#;
+
+    #[test]
+    fn test_key_position_inside_nested_object() {
+        let context = context_at(DOCUMENT, Position::new(4, 4)).unwrap();
+
+        assert_eq!(context.pointer, "/runtime");
+        assert_eq!(context.location, CursorLocation::Key);
+    }
+
+    #[test]
+    fn test_pointer_under_cursor() {
+        let contents = r#"{ "runtime": { "type": "docker" }, "ports": [ { "protocol": "tcp" } ] }"#;
+
+        // on the `type` key
+        assert_eq!(
+            pointer_at(contents, Position::new(0, 16)).as_deref(),
+            Some("/runtime/type")
+        );
+        // inside "tcp"
+        assert_eq!(
+            pointer_at(contents, Position::new(0, 62)).as_deref(),
+            Some("/ports/0/protocol")
+        );
+    }
+
+    #[test]
+    fn test_value_position_inside_array_element() {
+        // cursor just after `"protocol": `
+        let context = context_at(DOCUMENT, Position::new(8, 18)).unwrap();
+
+        assert_eq!(context.pointer, "/ports/1/protocol");
+        assert_eq!(context.location, CursorLocation::Value);
+    }
+}
@@ -1,26 +1,91 @@
+use std::panic::{self, UnwindSafe};
+
 use tower_lsp::lsp_types::Range;
-use tracing::{debug, instrument, trace, warn};
+use tracing::{debug, error, instrument, trace};
 
+use crate::document::Document;
 use crate::json_pointer;
 
 /// Resolves the range for a diagnostic from a JSON pointer
 #[instrument(skip(file_contents), fields(pointer = json_pointer))]
 pub fn from_pointer(json_pointer: &str, file_contents: &str) -> Range {
-    match json_pointer::into_range(json_pointer, file_contents) {
-        Some(range) => {
+    from_pointer_in(json_pointer, &Document::Text(file_contents))
+}
+
+/// `from_pointer` with line lookups through `document`
+pub fn from_pointer_in(json_pointer: &str, document: &Document) -> Range {
+    guarded(json_pointer, || {
+        json_pointer::into_range_in(json_pointer, document)
+    })
+}
+
+/// Runs a range resolver, falling back to the default range when it fails or panics
+/// A bug resolving one diagnostic's range must not prevent the other diagnostics
+pub(crate) fn guarded(
+    json_pointer: &str,
+    resolve: impl FnOnce() -> Option<Range> + UnwindSafe,
+) -> Range {
+    try_guarded(json_pointer, resolve).unwrap_or_else(|| {
+        debug!(
+            pointer = json_pointer,
+            "Failed to resolve range, using default"
+        );
+        Range::default()
+    })
+}
+
+/// Like `guarded`, but leaves the fallback to the caller
+pub(crate) fn try_guarded(
+    json_pointer: &str,
+    resolve: impl FnOnce() -> Option<Range> + UnwindSafe,
+) -> Option<Range> {
+    match panic::catch_unwind(resolve) {
+        Ok(Some(range)) => {
             trace!(
                 line = range.start.line,
                 character = range.start.character,
                 "Successfully resolved diagnostic range"
             );
-            range
+            Some(range)
         }
-        None => {
-            debug!(
-                pointer = json_pointer,
-                "Failed to resolve range, using default"
-            );
-            Range::default()
+        Ok(None) => None,
+        Err(_) => {
+            error!(pointer = json_pointer, "Range resolution panicked");
+            None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panicking_resolver_falls_back_to_default() {
+        let range = guarded("/runtime", || panic!("range resolution bug"));
+
+        assert_eq!(range, Range::default());
+    }
+
+    #[test]
+    fn test_multibyte_pointer_resolves_without_panic() {
+        let contents = "{\n  \"caf\u{e9}\": { \"\u{fc}ber\": 1 }\n}";
+
+        let range = from_pointer("/caf\u{e9}/\u{fc}ber", contents);
+
+        assert_eq!(range.start.line, 1);
+    }
+
+    #[test]
+    fn test_minified_document_resolves_column() {
+        let contents = r#"{"name":"api","runtime":{"name":"docker","port":"80"},"port":1}"#;
+
+        let range = from_pointer("/runtime/port", contents);
+
+        assert_eq!(range.start.line, 0);
+        assert_eq!(
+            range.start.character as usize,
+            contents.find("port").unwrap()
+        );
+    }
+}
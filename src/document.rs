@@ -0,0 +1,191 @@
+use tower_lsp::lsp_types::Position;
+use tracing::instrument;
+#[cfg(feature = "rope")]
+use tracing::trace;
+
+use crate::line_number::PositionEncoding;
+
+/// Documents at least this many bytes get a rope backed line index, see `Document::new`
+pub const ROPE_THRESHOLD: usize = 1 << 20;
+
+/// Text of a document with line/offset lookups for positioning diagnostics
+/// Build it once per document and reuse it for every range resolved in it
+pub enum Document<'a> {
+    /// Lines found by scanning the text on every lookup, cheapest for small documents
+    Text(&'a str),
+    /// Lines indexed by a rope, lookups are O(log n) in the document size
+    #[cfg(feature = "rope")]
+    Rope { text: &'a str, rope: ropey::Rope },
+}
+
+impl<'a> Document<'a> {
+    /// Scans `text` below `ROPE_THRESHOLD`, indexes it with a rope above it when the
+    /// `rope` feature is enabled
+    #[instrument(skip(text), fields(content_len = text.len()))]
+    pub fn new(text: &'a str) -> Self {
+        #[cfg(feature = "rope")]
+        if text.len() >= ROPE_THRESHOLD {
+            trace!("Indexing large document with a rope");
+            return Document::Rope {
+                text,
+                rope: ropey::Rope::from_str(text),
+            };
+        }
+
+        Document::Text(text)
+    }
+
+    pub fn text(&self) -> &'a str {
+        match self {
+            Document::Text(text) => text,
+            #[cfg(feature = "rope")]
+            Document::Rope { text, .. } => text,
+        }
+    }
+
+    /// Zero based line of byte `index`, indices past the end count as the last line
+    pub fn line_of(&self, index: usize) -> u32 {
+        let safe_index = floor_char_boundary(self.text(), index);
+
+        match self {
+            Document::Text(text) => text.as_bytes()[..safe_index]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count() as u32,
+            // only `\n` breaks lines, the crate is built without unicode or CR line breaks
+            #[cfg(feature = "rope")]
+            Document::Rope { rope, .. } => rope.byte_to_line(safe_index) as u32,
+        }
+    }
+
+    /// True when the document has no line break
+    pub fn is_single_line(&self) -> bool {
+        match self {
+            Document::Text(text) => !text.contains('\n'),
+            #[cfg(feature = "rope")]
+            Document::Rope { rope, .. } => rope.len_lines() == 1,
+        }
+    }
+
+    /// Byte index the zero based `line` starts at, None when the document has fewer lines
+    pub fn line_start(&self, line: u32) -> Option<usize> {
+        match self {
+            Document::Text(text) => {
+                if line == 0 {
+                    return Some(0);
+                }
+                text.match_indices('\n')
+                    .nth(line as usize - 1)
+                    .map(|(idx, _)| idx + 1)
+            }
+            #[cfg(feature = "rope")]
+            Document::Rope { rope, .. } => {
+                ((line as usize) < rope.len_lines()).then(|| rope.line_to_byte(line as usize))
+            }
+        }
+    }
+
+    /// Converts a `character` of `line`, counted in `encoding`, to a byte index
+    /// Returns None when the line does not exist, positions past a line end clamp to it
+    pub fn to_index(&self, line: u32, character: u32, encoding: PositionEncoding) -> Option<usize> {
+        let line_start = self.line_start(line)?;
+        let line_text = self.text()[line_start..]
+            .split('\n')
+            .next()
+            .unwrap_or_default();
+
+        let mut count = 0;
+        for (offset, ch) in line_text.char_indices() {
+            if count >= character as usize {
+                return Some(line_start + offset);
+            }
+            count += encoding.width(ch);
+        }
+
+        Some(line_start + line_text.len())
+    }
+
+    /// Converts a byte index to a position counting characters in `encoding`
+    pub fn to_position(&self, index: usize, encoding: PositionEncoding) -> Position {
+        let text = self.text();
+        let safe_index = floor_char_boundary(text, index);
+        let line = self.line_of(safe_index);
+        let line_start = match self {
+            Document::Text(text) => text[..safe_index].rfind('\n').map_or(0, |idx| idx + 1),
+            #[cfg(feature = "rope")]
+            Document::Rope { rope, .. } => rope.line_to_byte(line as usize),
+        };
+
+        Position {
+            line,
+            character: text[line_start..safe_index]
+                .chars()
+                .map(|ch| encoding.width(ch))
+                .sum::<usize>() as u32,
+        }
+    }
+}
+
+/// Clamps `index` into the contents and back onto a char boundary so slicing can't panic
+fn floor_char_boundary(raw_file_contents: &str, index: usize) -> usize {
+    let mut safe_index = index.min(raw_file_contents.len());
+    while !raw_file_contents.is_char_boundary(safe_index) {
+        safe_index -= 1;
+    }
+    safe_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookups_past_the_end() {
+        let document = Document::new("{\n  \"port\": 1\n}");
+
+        assert_eq!(document.line_start(2), Some(14));
+        assert_eq!(document.line_start(3), None);
+        assert_eq!(document.line_of(100), 2);
+        assert!(!document.is_single_line());
+        assert!(Document::new("{}").is_single_line());
+        assert_eq!(
+            document.to_position(100, PositionEncoding::Utf16),
+            Position::new(2, 1)
+        );
+    }
+
+    #[cfg(feature = "rope")]
+    #[test]
+    fn test_rope_matches_scanning_on_large_document() {
+        let members: Vec<String> = (0..40_000)
+            .map(|idx| format!("  \"key_{}\": \"caf\u{e9} {}\"", idx, idx))
+            .collect();
+        let contents = format!("{{\r\n{}\r\n}}", members.join(",\r\n"));
+        assert!(contents.len() >= ROPE_THRESHOLD);
+
+        let rope = Document::new(&contents);
+        assert!(matches!(rope, Document::Rope { .. }));
+        assert!(!rope.is_single_line());
+        let text = Document::Text(&contents);
+
+        // a spread of byte offsets, some inside multi-byte characters
+        let indices: Vec<usize> = (0..200).map(|n| n * (contents.len() / 199)).collect();
+
+        let scanned: Vec<_> = indices
+            .iter()
+            .map(|&idx| text.to_position(idx, PositionEncoding::Utf16))
+            .collect();
+        let indexed: Vec<_> = indices
+            .iter()
+            .map(|&idx| rope.to_position(idx, PositionEncoding::Utf16))
+            .collect();
+
+        assert_eq!(indexed, scanned);
+        for position in &scanned {
+            assert_eq!(
+                rope.to_index(position.line, position.character, PositionEncoding::Utf16),
+                text.to_index(position.line, position.character, PositionEncoding::Utf16)
+            );
+        }
+    }
+}
@@ -11,6 +11,14 @@ pub enum SchemaValidationError {
     #[error("Failed to read schema file: {0}")]
     SchemaFileReadError(#[from] std::io::Error),
 
+    /// No schema could be found for the given identifier
+    #[error("Schema not found: {0}")]
+    SchemaNotFound(String),
+
+    /// A remote schema could not be downloaded
+    #[error("Failed to fetch remote schema: {0}")]
+    SchemaFetchError(String),
+
     /// The provided JSON schema itself is invalid
     #[error("Invalid JSON schema provided: {0}")]
     InvalidSchemaError(String),
@@ -39,14 +47,22 @@ pub enum SchemaValidationError {
     #[error("Failed to calculate range for diagnostic at {0}")]
     RangeCalculationError(String),
 
-    /// File contents are empty or invalid
-    #[error("File contents are empty or invalid")]
+    /// File contents are empty or only whitespace
+    #[error("Document is empty")]
     EmptyFileContents,
 
     /// UTF-8 encoding error in file contents
     #[error("Invalid UTF-8 in file contents: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 
+    /// Document exceeds the size accepted for validation
+    #[error("Document exceeds the size limit of {limit} bytes")]
+    DocumentTooLarge { limit: usize },
+
+    /// Compressed payload could not be decompressed
+    #[error("Failed to decompress payload: {0}")]
+    DecompressionError(String),
+
     /// Generic diagnostic generation error
     #[error("Failed to generate diagnostic: {0}")]
     DiagnosticGenerationError(String),
@@ -80,4 +96,3 @@ impl SchemaValidationError {
         )
     }
 }
-
@@ -0,0 +1,110 @@
+use tower_lsp::lsp_types::{Position, Range};
+use tracing::{instrument, trace};
+
+use crate::document::Document;
+use crate::line_number::{self, PositionEncoding};
+use crate::{cursor, value_span};
+
+/// Path segment standing in for any array index, so keys of sibling elements compare equal
+const ANY_ELEMENT: &str = "*";
+
+/// Ranges of every key sharing the key path under `position`
+/// Array indices are ignored when comparing paths, so `"port"` in one element of
+/// `"ports"` highlights `"port"` in all of them
+/// Returns nothing unless the cursor is on a key of a well formed document
+#[instrument(skip(file_contents), fields(line = position.line, character = position.character))]
+pub fn key_occurrences(file_contents: &str, position: Position) -> Vec<Range> {
+    let Some(index) = line_number::to_index(file_contents, position.line, position.character)
+    else {
+        return Vec::new();
+    };
+
+    let mut keys = Vec::new();
+    let root = value_span::skip_whitespace(file_contents, 0);
+    collect_keys(file_contents, root, String::new(), &mut keys);
+
+    let Some((selected, _)) = keys
+        .iter()
+        .find(|(_, (start, end))| (*start..*end).contains(&index))
+    else {
+        trace!("Cursor is not on a key");
+        return Vec::new();
+    };
+
+    trace!(path = %selected, "Highlighting key path");
+    let document = Document::new(file_contents);
+    keys.iter()
+        .filter(|(path, _)| path == selected)
+        .map(|(_, (start, end))| Range {
+            start: document.to_position(*start, PositionEncoding::Utf16),
+            end: document.to_position(*end, PositionEncoding::Utf16),
+        })
+        .collect()
+}
+
+/// Key path (array indices replaced by `*`) and key span of every member below `value_start`
+fn collect_keys(
+    file_contents: &str,
+    value_start: usize,
+    path: String,
+    keys: &mut Vec<(String, value_span::Span)>,
+) {
+    match file_contents.as_bytes().get(value_start) {
+        Some(b'{') => {
+            for member in value_span::members(file_contents, value_start).unwrap_or_default() {
+                let path = format!("{}/{}", path, cursor::escape(&member.key));
+                keys.push((path.clone(), member.key_span));
+                collect_keys(file_contents, member.value_span.0, path, keys);
+            }
+        }
+        Some(b'[') => {
+            for (start, _) in value_span::elements(file_contents, value_start).unwrap_or_default() {
+                collect_keys(
+                    file_contents,
+                    start,
+                    format!("{}/{}", path, ANY_ELEMENT),
+                    keys,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_highlighted_across_array_elements() {
+        let contents = r#"{
+  "port": 1,
+  "ports": [
+    { "port": 8080, "name": "http" },
+    { "name": "admin", "port": 9090 }
+  ]
+}"#;
+
+        // cursor inside the "port" key of the first element
+        let ranges = key_occurrences(contents, Position::new(3, 8));
+        let highlighted: Vec<(u32, &str)> = ranges
+            .iter()
+            .map(|range| {
+                let line = contents.lines().nth(range.start.line as usize).unwrap();
+                let text = &line[range.start.character as usize..range.end.character as usize];
+                (range.start.line, text)
+            })
+            .collect();
+
+        // the top-level "port" is a different path
+        assert_eq!(highlighted, vec![(3, "\"port\""), (4, "\"port\"")]);
+    }
+
+    #[test]
+    fn test_values_are_not_highlighted() {
+        let contents = "{\n  \"name\": \"name\"\n}";
+
+        assert!(key_occurrences(contents, Position::new(1, 13)).is_empty());
+        assert_eq!(key_occurrences(contents, Position::new(1, 4)).len(), 1);
+    }
+}
@@ -0,0 +1,67 @@
+use serde_json::Value;
+use tracing::instrument;
+
+/// Markdown documentation for a schema node: title, description, type and examples
+/// Returns None when the node documents nothing worth showing
+#[instrument(skip(schema_node))]
+pub fn markdown(schema_node: &Value) -> Option<String> {
+    let mut sections = Vec::new();
+
+    if let Some(title) = schema_node.get("title").and_then(Value::as_str) {
+        sections.push(format!("**{}**", title));
+    }
+    if let Some(description) = schema_node.get("description").and_then(Value::as_str) {
+        sections.push(description.to_string());
+    }
+    match schema_node.get("type") {
+        Some(Value::String(kind)) => sections.push(format!("Type: `{}`", kind)),
+        Some(Value::Array(kinds)) => {
+            let kinds: Vec<String> = kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|k| format!("`{}`", k))
+                .collect();
+            sections.push(format!("Type: {}", kinds.join(" | ")));
+        }
+        _ => {}
+    }
+    if let Some(examples) = schema_node.get("examples").and_then(Value::as_array) {
+        let examples: Vec<String> = examples.iter().map(|e| format!("- `{}`", e)).collect();
+        if !examples.is_empty() {
+            sections.push(format!("Examples:\n{}", examples.join("\n")));
+        }
+    }
+
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples_rendered_in_hover() {
+        let node = serde_json::json!({
+            "type": "string",
+            "description": "Container image",
+            "examples": ["nginx", "redis:7"]
+        });
+
+        assert_eq!(
+            markdown(&node).as_deref(),
+            Some("Container image\n\nType: `string`\n\nExamples:\n- `\"nginx\"`\n- `\"redis:7\"`")
+        );
+    }
+
+    #[test]
+    fn test_type_lists_rendered_unquoted() {
+        let node = serde_json::json!({ "type": ["string", "null"] });
+
+        assert_eq!(markdown(&node).as_deref(), Some("Type: `string` | `null`"));
+    }
+
+    #[test]
+    fn test_undocumented_node_has_no_hover() {
+        assert_eq!(markdown(&serde_json::json!({ "minimum": 1 })), None);
+    }
+}
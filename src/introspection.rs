@@ -0,0 +1,430 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::{debug, instrument, trace};
+
+/// Resolves the schema node describing the instance value at `json_pointer`
+/// Follows `properties`, `patternProperties`, `items` and `additionalProperties`, plus local `$ref`s
+///
+/// Returns None when the schema does not describe the pointed-at value
+#[instrument(skip(json_schema), fields(pointer = json_pointer))]
+pub fn resolve<'a>(json_schema: &'a Value, json_pointer: &str) -> Option<&'a Value> {
+    let mut node = follow_ref(json_schema, json_schema)?;
+
+    // first split item is the empty root segment
+    for segment in json_pointer.split('/').skip(1) {
+        let segment = unescape(segment);
+        node = child(node, &segment)?;
+        node = follow_ref(json_schema, node)?;
+
+        trace!(segment = %segment, "Resolved schema node for segment");
+    }
+
+    Some(node)
+}
+
+/// Resolves the schema node at `schema_path`, as reported by a validation error
+/// Unlike `resolve` the path names schema keywords (eg /properties/runtime/$ref),
+/// a `$ref` segment continues at the local reference target
+#[instrument(skip(json_schema), fields(path = schema_path))]
+pub fn resolve_schema_path<'a>(json_schema: &'a Value, schema_path: &str) -> Option<&'a Value> {
+    let mut node = json_schema;
+
+    for segment in schema_path.split('/').skip(1) {
+        node = match segment {
+            "$ref" => follow_ref(json_schema, node)?,
+            _ => {
+                let segment = unescape(segment);
+                match node {
+                    Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                    _ => node.get(&segment)?,
+                }
+            }
+        };
+    }
+
+    Some(node)
+}
+
+/// `pattern` compiled once and shared by every later lookup, None when `regex` does not
+/// support it (eg lookaround)
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    static COMPILED: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut compiled = COMPILED
+        .get_or_init(Default::default)
+        .lock()
+        .expect("pattern cache poisoned");
+
+    compiled
+        .entry(pattern.to_owned())
+        .or_insert_with(|| match Regex::new(pattern) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                debug!(pattern = %pattern, error = %e, "Unsupported patternProperties regex");
+                None
+            }
+        })
+        .clone()
+}
+
+/// Returns the sub-schema governing `segment` within `node`
+fn child<'a>(node: &'a Value, segment: &str) -> Option<&'a Value> {
+    if let Some(property) = node.get("properties").and_then(|p| p.get(segment)) {
+        return Some(property);
+    }
+
+    // dynamic keys, the first pattern matching the key governs it
+    if let Some(patterns) = node.get("patternProperties").and_then(Value::as_object) {
+        let matching = patterns
+            .iter()
+            .find(|(pattern, _)| pattern_regex(pattern).is_some_and(|r| r.is_match(segment)));
+        if let Some((pattern, subschema)) = matching {
+            trace!(pattern = %pattern, "Key matched patternProperties");
+            return Some(subschema);
+        }
+    }
+
+    if let Ok(index) = segment.parse::<usize>() {
+        match node.get("items") {
+            Some(Value::Array(tuple)) => return tuple.get(index),
+            Some(items @ Value::Object(_)) => return Some(items),
+            _ => {}
+        }
+    }
+
+    // additionalProperties: true/false carries no schema worth introspecting
+    node.get("additionalProperties").filter(|v| v.is_object())
+}
+
+/// Most `$ref` hops followed for a single node before giving up
+const MAX_REF_DEPTH: usize = 32;
+
+/// Follows a local `$ref` (eg #/definitions/Port) to its target node
+/// Chains of refs are followed too; a cycle (or a chain deeper than `MAX_REF_DEPTH`)
+/// returns None instead of looping forever
+fn follow_ref<'a>(json_schema: &'a Value, node: &'a Value) -> Option<&'a Value> {
+    let mut node = node;
+    let mut visited = HashSet::new();
+
+    while let Some(reference) = node.get("$ref").and_then(Value::as_str) {
+        if !reference.starts_with('#') {
+            debug!(reference, "Remote $ref is not introspected");
+            return Some(node);
+        }
+        if !visited.insert(reference) || visited.len() > MAX_REF_DEPTH {
+            debug!(reference, "Recursive $ref chain, not following");
+            return None;
+        }
+
+        node = match local_target(json_schema, reference) {
+            Some(target) => target,
+            None => {
+                debug!(reference, "Unresolvable local $ref");
+                return None;
+            }
+        };
+    }
+
+    Some(node)
+}
+
+/// True for a local `$ref` (eg #/definitions/Fooo) naming no node of `json_schema`
+/// Remote refs are not checked, they are never introspected
+pub fn is_dangling_ref(json_schema: &Value, reference: &str) -> bool {
+    reference.starts_with('#') && local_target(json_schema, reference).is_none()
+}
+
+/// Node of `json_schema` the local `reference` names: the root for `#`, a percent-encoded
+/// Json Pointer for `#/...`, otherwise the plain name of an `$anchor`
+fn local_target<'a>(json_schema: &'a Value, reference: &str) -> Option<&'a Value> {
+    let fragment = reference.strip_prefix('#')?;
+    if fragment.is_empty() {
+        return Some(json_schema);
+    }
+    if fragment.starts_with('/') {
+        return json_schema.pointer(&percent_decode(fragment)?);
+    }
+
+    let mut target = None;
+    visit_schema(json_schema, |pointer, keyword, value| {
+        // draft 7 and older spell anchors as a fragment-only `$id`
+        let anchor = match (keyword, value.as_str()) {
+            ("$anchor", Some(anchor)) => anchor,
+            ("$id", Some(id)) => id.strip_prefix('#').unwrap_or_default(),
+            _ => return,
+        };
+        if target.is_none() && anchor == fragment {
+            let parent = pointer.rsplit_once('/').map_or("", |(parent, _)| parent);
+            target = json_schema.pointer(parent);
+        }
+    });
+    target
+}
+
+/// Local `$ref` (eg `#/properties/api`) to the node `resolve` finds for `json_pointer`,
+/// so the node can be validated with the rest of `json_schema` around it
+pub fn resolve_reference(json_schema: &Value, json_pointer: &str) -> Option<String> {
+    let node = resolve(json_schema, json_pointer)?;
+
+    let mut reference = None;
+    walk(json_schema, (), |pointer, _, value, ()| {
+        if reference.is_some() {
+            return None;
+        }
+        if std::ptr::eq(value, node) {
+            reference = Some(format!("#{}", percent_encode(pointer)));
+            return None;
+        }
+        Some(())
+    });
+    reference
+}
+
+/// Escapes a Json Pointer for use as URI fragment, `percent_decode` reverses it
+fn percent_encode(pointer: &str) -> String {
+    let mut encoded = String::with_capacity(pointer.len());
+    for byte in pointer.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decodes the `%XX` escapes of a URI fragment, None when they are not valid UTF-8
+fn percent_decode(fragment: &str) -> Option<String> {
+    let bytes = fragment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = (bytes[idx] == b'%')
+            .then(|| fragment.get(idx + 1..idx + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Keywords whose value is instance data rather than subschemas
+const DATA_KEYWORDS: &[&str] = &["enum", "const", "default", "examples", "$comment"];
+
+/// Keywords mapping names to subschemas, their members are not keywords themselves
+const NAMED_SCHEMAS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "definitions",
+    "$defs",
+    "dependentSchemas",
+    "dependencies",
+];
+
+/// Calls `visit` with the Json Pointer, name and value of every keyword in `json_schema`
+/// and its subschemas. Instance data under `enum`, `const`, `default`, ... is not walked
+pub fn visit_schema<'a>(json_schema: &'a Value, mut visit: impl FnMut(&str, &'a str, &'a Value)) {
+    // the walk state tells whether the value is a schema, ie its members are keywords
+    walk(json_schema, true, |pointer, key, value, in_schema| {
+        let Some(keyword) = key.filter(|_| in_schema) else {
+            return Some(true);
+        };
+        visit(pointer, keyword, value);
+        if DATA_KEYWORDS.contains(&keyword) {
+            None
+        } else {
+            Some(!NAMED_SCHEMAS.contains(&keyword))
+        }
+    });
+}
+
+/// Depth first walk over `value` and every value nested in it
+/// `visit` gets each value with its Json Pointer, its member key (None for array elements
+/// and the root) and the state its parent returned; it returns the state for the value's
+/// own children, or None to skip them
+pub(crate) fn walk<'a, S: Copy>(
+    value: &'a Value,
+    state: S,
+    mut visit: impl FnMut(&str, Option<&'a str>, &'a Value, S) -> Option<S>,
+) {
+    let mut stack = vec![(String::new(), None, value, state)];
+
+    while let Some((pointer, key, node, state)) = stack.pop() {
+        let Some(state) = visit(&pointer, key, node, state) else {
+            continue;
+        };
+        match node {
+            Value::Object(object) => stack.extend(object.iter().map(|(key, child)| {
+                let child_pointer = format!("{}/{}", pointer, crate::cursor::escape(key));
+                (child_pointer, Some(key.as_str()), child, state)
+            })),
+            Value::Array(items) => stack.extend(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, child)| (format!("{}/{}", pointer, idx), None, child, state)),
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// Unescapes a single RFC 6901 pointer segment
+pub(crate) fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_nested_properties_and_items() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+
+        let docker = resolve(&schema, "/runtime/docker").expect("docker node");
+        assert_eq!(docker["required"], serde_json::json!(["image"]));
+
+        let port = resolve(&schema, "/ports/3/containerPort").expect("port node");
+        assert_eq!(port["type"], "integer");
+
+        assert!(resolve(&schema, "/runtime/unknown").is_none());
+    }
+
+    #[test]
+    fn test_follows_local_refs() {
+        let schema = serde_json::json!({
+            "definitions": {
+                "port": { "type": "integer" }
+            },
+            "properties": {
+                "port": { "$ref": "#/definitions/port" }
+            }
+        });
+
+        let port = resolve(&schema, "/port").expect("port node");
+        assert_eq!(port["type"], "integer");
+    }
+
+    #[test]
+    fn test_recursive_refs_terminate() {
+        let schema = serde_json::json!({
+            "definitions": {
+                "tree": {
+                    "properties": {
+                        "value": { "type": "integer" },
+                        "children": { "type": "array", "items": { "$ref": "#/definitions/tree" } }
+                    }
+                },
+                "ping": { "$ref": "#/definitions/pong" },
+                "pong": { "$ref": "#/definitions/ping" }
+            },
+            "properties": {
+                "root": { "$ref": "#/definitions/tree" },
+                "loop": { "$ref": "#/definitions/ping" }
+            }
+        });
+
+        // self-referencing structures resolve at any depth
+        let value = resolve(&schema, "/root/children/0/children/2/value").expect("tree node");
+        assert_eq!(value["type"], "integer");
+
+        // a ref chain that never reaches a schema gives up instead of hanging
+        assert!(resolve(&schema, "/loop").is_none());
+    }
+
+    #[test]
+    fn test_dangling_ref_resolves_to_none() {
+        let schema = serde_json::json!({
+            "definitions": { "Foo": { "type": "string" } },
+            "properties": {
+                "name": { "$ref": "#/definitions/Fooo" },
+                "alias": { "$ref": "#/definitions/Foo" }
+            }
+        });
+
+        assert!(resolve(&schema, "/name").is_none());
+        assert!(resolve(&schema, "/alias").is_some());
+        assert!(is_dangling_ref(&schema, "#/definitions/Fooo"));
+        assert!(!is_dangling_ref(&schema, "https://example.com/remote.json"));
+    }
+
+    #[test]
+    fn test_anchor_and_encoded_refs_are_not_dangling() {
+        let schema = serde_json::json!({
+            "$defs": {
+                "node": { "$anchor": "node", "type": "object" },
+                "a b": { "type": "string" }
+            },
+            "properties": {
+                "tree": { "$ref": "#node" },
+                "label": { "$ref": "#/$defs/a%20b" },
+                "anchors": { "enum": [{ "$anchor": "data" }] }
+            }
+        });
+
+        assert!(!is_dangling_ref(&schema, "#node"));
+        assert!(!is_dangling_ref(&schema, "#/$defs/a%20b"));
+        assert!(is_dangling_ref(&schema, "#nodes"));
+        // instance data under enum does not declare anchors
+        assert!(is_dangling_ref(&schema, "#data"));
+        assert_eq!(resolve(&schema, "/tree").unwrap()["type"], "object");
+        assert_eq!(resolve(&schema, "/label").unwrap()["type"], "string");
+    }
+
+    #[test]
+    fn test_pattern_properties_govern_dynamic_keys() {
+        let schema = serde_json::json!({
+            "properties": {
+                "env": {
+                    "properties": { "PATH": { "type": "array" } },
+                    "patternProperties": {
+                        "^[A-Z_]+$": { "type": "string" },
+                        "^x-": { "type": "object" }
+                    }
+                }
+            }
+        });
+
+        let variable = resolve(&schema, "/env/LOG_LEVEL").expect("pattern node");
+        assert_eq!(variable["type"], "string");
+
+        let extension = resolve(&schema, "/env/x-team").expect("pattern node");
+        assert_eq!(extension["type"], "object");
+
+        // declared properties take precedence over patterns
+        assert_eq!(resolve(&schema, "/env/PATH").unwrap()["type"], "array");
+        assert!(resolve(&schema, "/env/lower").is_none());
+    }
+
+    #[test]
+    fn test_resolves_schema_path_through_refs() {
+        let schema = serde_json::json!({
+            "definitions": {
+                "docker": { "title": "DockerRuntime", "type": "object" }
+            },
+            "properties": {
+                "runtime": { "anyOf": [{ "$ref": "#/definitions/docker" }] }
+            }
+        });
+
+        let docker =
+            resolve_schema_path(&schema, "/properties/runtime/anyOf/0/$ref").expect("docker node");
+        assert_eq!(docker["title"], "DockerRuntime");
+
+        assert!(resolve_schema_path(&schema, "/properties/missing").is_none());
+    }
+}
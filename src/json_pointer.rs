@@ -1,7 +1,9 @@
 use tower_lsp::lsp_types::{Position, Range};
 use tracing::{debug, instrument, trace, warn};
 
-use crate::{line_number, pointer_index};
+use crate::document::Document;
+use crate::line_number::{self, PositionEncoding};
+use crate::value_span;
 
 /// Converts Json Pointer to start Position, end Position
 /// Takes a &str JsonPointer and the original raw_file_contents,
@@ -11,25 +13,16 @@ use crate::{line_number, pointer_index};
     content_len = raw_file_contents.len()
 ))]
 pub fn into_range(json_pointer: &str, raw_file_contents: &str) -> Option<Range> {
-    trace!("Converting JSON pointer to range");
-
-    // json pointer looks like it gives the parent object//parent node of the error
-
-    // since json pointer starts with /root/node/node/etc
-    // iterate through / and then search for match
+    into_range_in(json_pointer, &Document::Text(raw_file_contents))
+}
 
-    // within json_pointer
-    // convert to iterator
-    // for each iteration
-    //      find index of first char of matching iteration of json_pointer
-    //      drop all string items before x
-    //      increment summation index by index of that match
-    // once final iteration occurs -> Found match... search for (in order { (then find next closing
-    // symbol = } ), OR NEWLINE ... only NEWLINE for now)
-    // find distance until NEWLINE / end terminator
-    // that == end position of range
+/// `into_range` with line lookups through `document`, built once for many pointers
+#[instrument(skip(document), fields(pointer = json_pointer))]
+pub fn into_range_in(json_pointer: &str, document: &Document) -> Option<Range> {
+    trace!("Converting JSON pointer to range");
+    let raw_file_contents = document.text();
 
-    let index_summation = pointer_index::calculate(json_pointer, raw_file_contents);
+    let index_summation = pointer_index(json_pointer, raw_file_contents);
 
     debug!(
         pointer = json_pointer,
@@ -37,8 +30,22 @@ pub fn into_range(json_pointer: &str, raw_file_contents: &str) -> Option<Range>
         "Calculated index for JSON pointer"
     );
 
+    // minified single-line documents would put every error at the same spot, point at the
+    // resolved segment's column instead
+    if document.is_single_line() {
+        let position = document.to_position(index_summation, PositionEncoding::Utf16);
+        trace!(
+            character = position.character,
+            "Single-line document, using column"
+        );
+        return Some(Range {
+            start: position,
+            end: position,
+        });
+    }
+
     // count byte occurences of newline char for the line position.
-    let line_number = line_number::from_index(raw_file_contents, index_summation);
+    let line_number = document.line_of(index_summation);
 
     trace!(line = line_number, "Calculated line number from index");
 
@@ -55,3 +62,310 @@ pub fn into_range(json_pointer: &str, raw_file_contents: &str) -> Option<Range>
         },
     })
 }
+
+/// Byte index a diagnostic for `json_pointer` starts at: just past the opening quote of
+/// the key naming the value (at an unquoted key), or the value itself for array elements,
+/// which have no key
+/// A pointer missing from the text falls back to its nearest existing ancestor, the root
+/// pointer to the document start
+fn pointer_index(json_pointer: &str, raw_file_contents: &str) -> usize {
+    let mut pointer = json_pointer;
+
+    loop {
+        match value_span::locate_member(pointer, raw_file_contents) {
+            Some(member) if member.key_span != member.value_span => {
+                let quoted = raw_file_contents.as_bytes()[member.key_span.0] == b'"';
+                return member.key_span.0 + usize::from(quoted);
+            }
+            Some(member) => return member.value_span.0,
+            None => {
+                let Some((parent, _)) = pointer.rsplit_once('/') else {
+                    return 0;
+                };
+                trace!(pointer, "Pointer not in text, trying its parent");
+                pointer = parent;
+            }
+        }
+    }
+}
+
+/// Range of the key (including quotes) named by the last segment of `json_pointer`
+/// Returns None for the root pointer, array elements, or keys missing from the text
+#[instrument(skip(raw_file_contents), fields(pointer = json_pointer))]
+pub fn key_range(json_pointer: &str, raw_file_contents: &str) -> Option<Range> {
+    key_range_in(json_pointer, &Document::Text(raw_file_contents))
+}
+
+/// `key_range` with line lookups through `document`
+pub fn key_range_in(json_pointer: &str, document: &Document) -> Option<Range> {
+    let member = value_span::locate_member(json_pointer, document.text())?;
+    if member.key.is_empty() && member.key_span == member.value_span {
+        trace!("Pointer names an array element, no key to highlight");
+        return None;
+    }
+
+    Some(span_range(document, member.key_span))
+}
+
+/// Range of the whole value literal at `json_pointer`, brackets and quotes included
+#[instrument(skip(raw_file_contents), fields(pointer = json_pointer))]
+pub fn value_range(json_pointer: &str, raw_file_contents: &str) -> Option<Range> {
+    value_range_in(json_pointer, &Document::Text(raw_file_contents))
+}
+
+/// `value_range` with line lookups through `document`
+pub fn value_range_in(json_pointer: &str, document: &Document) -> Option<Range> {
+    let span = value_span::locate(json_pointer, document.text())?;
+    Some(span_range(document, span))
+}
+
+/// Range of the byte span `(start, end)` in `document`
+fn span_range(document: &Document, (start, end): (usize, usize)) -> Range {
+    Range {
+        start: document.to_position(start, PositionEncoding::Utf16),
+        end: document.to_position(end, PositionEncoding::Utf16),
+    }
+}
+
+/// Position just after the value at `json_pointer`, past its closing bracket or quote
+/// Where a code action appends after the value, eg a trailing property
+#[instrument(skip(raw_file_contents), fields(pointer = json_pointer))]
+pub fn value_end_position(json_pointer: &str, raw_file_contents: &str) -> Option<Position> {
+    value_end_position_in(json_pointer, &Document::Text(raw_file_contents))
+}
+
+/// `value_end_position` with line lookups through `document`
+pub fn value_end_position_in(json_pointer: &str, document: &Document) -> Option<Position> {
+    let (_, end) = value_span::locate(json_pointer, document.text())?;
+    Some(document.to_position(end, PositionEncoding::Utf16))
+}
+
+/// Inverse of the range functions: byte span `[start, end)` of `range` in the contents
+/// Characters are UTF-16 code units like every LSP position
+/// Returns None when either end is past its line or the document, or the range is reversed
+pub fn range_to_byte_span(range: &Range, raw_file_contents: &str) -> Option<(usize, usize)> {
+    // to_index clamps positions past a line end, only accept ones that map back exactly
+    let index = |position: Position| {
+        line_number::to_index(raw_file_contents, position.line, position.character)
+            .filter(|&idx| line_number::to_position(raw_file_contents, idx) == position)
+    };
+
+    let (start, end) = (index(range.start)?, index(range.end)?);
+    (start <= end).then_some((start, end))
+}
+
+/// Where a new property goes in the object at `json_pointer`: just after its last member,
+/// or just inside the `{` when it has none. The flag tells whether the inserted text has to
+/// start with a comma, it is false for empty objects and after a trailing comma
+/// Returns None when the pointer does not name an object
+#[instrument(skip(raw_file_contents), fields(pointer = json_pointer))]
+pub fn object_insertion_point(
+    json_pointer: &str,
+    raw_file_contents: &str,
+) -> Option<(Position, bool)> {
+    let (index, needs_leading_comma) = object_insertion_index(json_pointer, raw_file_contents)?;
+    Some((
+        line_number::to_position(raw_file_contents, index),
+        needs_leading_comma,
+    ))
+}
+
+/// `object_insertion_point` as a byte index into the contents
+pub(crate) fn object_insertion_index(
+    json_pointer: &str,
+    raw_file_contents: &str,
+) -> Option<(usize, bool)> {
+    let (object_start, _) = value_span::locate(json_pointer, raw_file_contents)?;
+    let members = value_span::members(raw_file_contents, object_start)?;
+
+    let (index, needs_leading_comma) = match members.last() {
+        None => (object_start + 1, false),
+        Some(last) => {
+            let after_value = value_span::skip_whitespace(raw_file_contents, last.value_span.1);
+            if raw_file_contents.as_bytes().get(after_value) == Some(&b',') {
+                trace!("Object ends with a trailing comma");
+                (after_value + 1, false)
+            } else {
+                (last.value_span.1, true)
+            }
+        }
+    };
+
+    Some((index, needs_leading_comma))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_end_position_after_terminator() {
+        let contents = r#"{
+  "name": "api",
+  "runtime": {
+    "image": { "tag": "1" }
+  },
+  "ports": [80, [443]]
+}"#;
+        let at = |line: u32, character: u32| Some(Position { line, character });
+
+        assert_eq!(value_end_position("/name", contents), at(1, 15));
+        assert_eq!(value_end_position("/runtime", contents), at(4, 3));
+        assert_eq!(value_end_position("/ports", contents), at(5, 22));
+        assert_eq!(value_end_position("/missing", contents), None);
+    }
+
+    #[test]
+    fn test_object_insertion_point_follows_last_member() {
+        let contents = r#"{
+  "name": "api",
+  "runtime": {},
+  "labels": { "team": "core", },
+  "ports": [80]
+}"#;
+        let at = |line: u32, character: u32| Position { line, character };
+
+        assert_eq!(
+            object_insertion_point("", contents),
+            Some((at(4, 15), true))
+        );
+        assert_eq!(
+            object_insertion_point("/runtime", contents),
+            Some((at(2, 14), false))
+        );
+        assert_eq!(
+            object_insertion_point("/labels", contents),
+            Some((at(3, 29), false))
+        );
+        assert_eq!(object_insertion_point("/ports", contents), None);
+    }
+
+    #[test]
+    fn test_byte_span_round_trips_with_ranges() {
+        let contents = include_str!("../testing/test_control.json");
+
+        for pointer in [
+            "/service",
+            "/runtime",
+            "/runtime/docker/tag",
+            "/ports/0",
+            "/env",
+        ] {
+            let range = value_range(pointer, contents).unwrap();
+            let (start, end) = range_to_byte_span(&range, contents).unwrap();
+
+            assert_eq!(
+                value_span::locate(pointer, contents),
+                Some((start, end)),
+                "{}",
+                pointer
+            );
+        }
+
+        let range = key_range("/runtime/docker/mage", contents).unwrap();
+        let (start, end) = range_to_byte_span(&range, contents).unwrap();
+        assert_eq!(&contents[start..end], "\"mage\"");
+    }
+
+    #[test]
+    fn test_byte_span_counts_utf16_and_rejects_out_of_bounds() {
+        let contents = "{\n  \"caf\u{e9}\": \"\u{1f600}\"\n}";
+        let range = value_range("/caf\u{e9}", contents).unwrap();
+
+        let (start, end) = range_to_byte_span(&range, contents).unwrap();
+        assert_eq!(&contents[start..end], "\"\u{1f600}\"");
+
+        let at = |line, character| Position { line, character };
+        let span = |start, end| range_to_byte_span(&Range { start, end }, contents);
+        assert_eq!(span(at(1, 2), at(1, 40)), None);
+        assert_eq!(span(at(3, 0), at(3, 0)), None);
+        assert_eq!(span(at(1, 4), at(1, 2)), None);
+    }
+
+    #[test]
+    fn test_missing_pointers_resolve_to_nearest_ancestor() {
+        let contents = r#"{
+  "a": {
+    "x": true
+  },
+  "b": {
+    "enabled": true
+  }
+}"#;
+        let line = |pointer: &str| into_range(pointer, contents).unwrap().start.line;
+
+        // "enabled" only exists under "b", "/a/enabled" must not resolve there
+        assert_eq!(line("/a/enabled"), 1);
+        assert_eq!(line("/b/enabled"), 5);
+        assert_eq!(line("/missing/deeper"), 0);
+    }
+
+    #[test]
+    fn test_unquoted_json5_keys_resolve() {
+        let contents = r#"{
+  // JSON5 allows unquoted keys
+  image: "nginx",
+  runtime: {
+    imagePort: 80,
+    port: "80",
+  },
+}"#;
+
+        let index = pointer_index("/runtime/port", contents);
+        assert!(contents[index..].starts_with("port:"));
+        assert_eq!(into_range("/runtime/port", contents).unwrap().start.line, 5);
+        assert_eq!(into_range("/image", contents).unwrap().start.line, 2);
+
+        let single_line = "{ name: \"api\", port: \"80\" }";
+        assert_eq!(
+            into_range("/port", single_line).unwrap().start,
+            Position::new(0, 15)
+        );
+    }
+
+    #[test]
+    fn test_numeric_segments_follow_parent_type() {
+        let contents = r#"{
+  "byIndex": [
+    "1",
+    { "port": 10 }
+  ],
+  "byKey": {
+    "1": "one",
+    "0": { "port": 20 }
+  }
+}"#;
+        let line = |pointer: &str| into_range(pointer, contents).unwrap().start.line;
+
+        // array parent: position, not the string "1" or the 0 in 10
+        assert_eq!(line("/byIndex/0"), 2);
+        assert_eq!(line("/byIndex/1"), 3);
+        assert_eq!(line("/byIndex/1/port"), 3);
+        // object parent: the key named "0"
+        assert_eq!(line("/byKey/0"), 7);
+        assert_eq!(line("/byKey/0/port"), 7);
+        assert_eq!(line("/byKey/1"), 6);
+    }
+
+    #[test]
+    fn test_array_elements_resolve_to_their_lines() {
+        let contents = r#"{
+  "ports": [
+    { "containerPort": 8080, "protocol": "tcp" },
+    {
+      "containerPort": 9090,
+      "protocol": "udp"
+    }
+  ],
+  "protocol": "http"
+}"#;
+        let line = |pointer: &str| into_range(pointer, contents).unwrap().start.line;
+
+        assert_eq!(line("/ports/0/protocol"), 2);
+        assert_eq!(line("/ports/1"), 3);
+        assert_eq!(line("/ports/1/containerPort"), 4);
+        assert_eq!(line("/ports/1/protocol"), 5);
+        // the root member, not the same key inside the elements before it
+        assert_eq!(line("/protocol"), 8);
+    }
+}
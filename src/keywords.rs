@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use jsonschema::paths::{LazyLocation, Location};
+use jsonschema::{Keyword, ValidationError, ValidationOptions};
+use serde_json::Value;
+use tracing::trace;
+
+/// Check behind a custom schema keyword: receives the keyword's value in the schema and the
+/// instance it applies to, returns why the instance violates it
+pub type KeywordHandler = Arc<dyn Fn(&Value, &Value) -> Option<String> + Send + Sync>;
+
+/// Extension keywords (eg `x-internal`) validated by embedder supplied handlers
+/// Violations become regular diagnostics, their keyword is the registered name
+#[derive(Clone, Default)]
+pub struct CustomKeywords {
+    handlers: Vec<(String, KeywordHandler)>,
+}
+
+impl CustomKeywords {
+    /// Runs `handler` wherever a schema node declares `name`
+    /// Registering a name again replaces its handler
+    pub fn register_keyword(
+        &mut self,
+        name: impl Into<String>,
+        handler: impl Fn(&Value, &Value) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let name = name.into();
+        self.handlers.retain(|(registered, _)| *registered != name);
+        self.handlers.push((name, Arc::new(handler)));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Adds every registered keyword to the validator `options`
+    pub(crate) fn apply(&self, mut options: ValidationOptions) -> ValidationOptions {
+        for (name, handler) in &self.handlers {
+            let handler = handler.clone();
+            options = options.with_keyword(name.clone(), move |_, schema_value, location| {
+                Ok(Box::new(HandlerKeyword {
+                    handler: handler.clone(),
+                    schema_value: schema_value.clone(),
+                    location,
+                }))
+            });
+        }
+        options
+    }
+}
+
+/// One occurrence of a custom keyword in the schema
+struct HandlerKeyword {
+    handler: KeywordHandler,
+    schema_value: Value,
+    // schema location of the keyword, reported as the error's schema path
+    location: Location,
+}
+
+impl Keyword for HandlerKeyword {
+    fn validate<'i>(
+        &self,
+        instance: &'i Value,
+        location: &LazyLocation,
+    ) -> Result<(), ValidationError<'i>> {
+        match (self.handler)(&self.schema_value, instance) {
+            None => Ok(()),
+            Some(message) => {
+                trace!(keyword = %self.location, message = %message, "Custom keyword failed");
+                Err(ValidationError::custom(
+                    self.location.clone(),
+                    location.into(),
+                    instance,
+                    message,
+                ))
+            }
+        }
+    }
+
+    fn is_valid(&self, instance: &Value) -> bool {
+        (self.handler)(&self.schema_value, instance).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::{CompiledSchema, SchemaValidator, ValidationKind};
+
+    fn forbidding() -> CustomKeywords {
+        let mut keywords = CustomKeywords::default();
+        keywords.register_keyword("x-forbidden", |forbidden, instance| {
+            (forbidden == instance).then(|| format!("{} is reserved", instance))
+        });
+        keywords
+    }
+
+    #[test]
+    fn test_custom_keyword_rejects_value() {
+        let schema = serde_json::json!({
+            "properties": { "user": { "type": "string", "x-forbidden": "root" } }
+        });
+        let contents = r#"{ "user": "root" }"#;
+        let instance: Value = serde_json::from_str(contents).unwrap();
+        let keywords = forbidding();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .with_keywords(&keywords)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].instance_path, "/user");
+        assert_eq!(findings[0].message, r#""root" is reserved"#);
+        assert_eq!(
+            findings[0].kind,
+            ValidationKind::Other("x-forbidden".into())
+        );
+
+        // without the handler the keyword is an annotation
+        let unchecked = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+        assert!(unchecked.is_empty());
+
+        let compiled = CompiledSchema::with_keywords(schema, &keywords).unwrap();
+        assert_eq!(compiled.validate(contents).unwrap().len(), 1);
+        assert!(
+            compiled
+                .validate(r#"{ "user": "admin" }"#)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -1,15 +1,46 @@
+pub mod backend;
+pub mod cli;
+pub mod code_action;
+pub mod completion;
+pub mod config;
+pub mod cursor;
 pub mod diagnostic_range;
+pub mod document;
 pub mod error;
+pub mod highlight;
+pub mod hover;
+pub mod introspection;
 pub mod json_pointer;
+pub mod keywords;
 pub mod line_number;
+pub mod markdown;
 pub mod parsing;
-pub mod pointer_index;
+pub mod prelude;
+pub mod render;
+pub mod resolver;
+pub mod sink;
+pub mod symbols;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod validation;
+pub mod value_span;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
-use tower_lsp::lsp_types::Diagnostic;
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
 use tracing::{debug, info, instrument, warn};
 
-use crate::{error::SchemaValidationError, parsing::ParsedContent, validation::SchemaValidator};
+use crate::{
+    config::Config,
+    document::Document,
+    error::{SchemaValidationError, ValidationResult},
+    parsing::ParsedContent,
+    resolver::SchemaResolver,
+    validation::SchemaValidator,
+};
 
 /// Takes Json Schema (From HAshmap on BAckend Struct)
 /// Returns All Errors from schema validation as Lsp Daignostics with Error Severity
@@ -17,37 +48,675 @@ use crate::{error::SchemaValidationError, parsing::ParsedContent, validation::Sc
 /// Improvements TODO
 /// - Retrieve Actual Range for Diagnostic (Maps to File_contents) from JsonPointer
 /// - Use above function with SchemaPath to get hint from SchemaPath
-#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
 pub fn validate_liberally(
     json_schema: &serde_json::Value,
     file_contents: &str,
+) -> Result<Vec<Diagnostic>, SchemaValidationError> {
+    validate_with_config(json_schema, file_contents, Config::default_ref())
+}
+
+/// Same as `validate_liberally`, with the optional validation modes from `config`
+#[instrument(
+    skip(json_schema, file_contents, config),
+    fields(content_len = file_contents.len(), draft = ?validation::detect_draft(json_schema))
+)]
+pub fn validate_with_config(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    config: &Config,
 ) -> Result<Vec<Diagnostic>, SchemaValidationError> {
     info!("Starting schema validation");
+    validate_document(json_schema, file_contents, DocumentValidation::new(config))
+}
+
+/// Same as `validate_with_config` for the schema identified by `schema_id`
+/// With `schema_id_in_diagnostics` set, every diagnostic names that schema
+#[instrument(skip(json_schema, file_contents, config), fields(content_len = file_contents.len()))]
+pub fn validate_with_schema_id(
+    json_schema: &serde_json::Value,
+    schema_id: &str,
+    file_contents: &str,
+    config: &Config,
+) -> ValidationResult<Vec<Diagnostic>> {
+    info!("Starting schema validation");
+    validate_document(
+        json_schema,
+        file_contents,
+        DocumentValidation {
+            schema_id: Some(schema_id),
+            ..DocumentValidation::new(config)
+        },
+    )
+}
+
+/// Same as `validate_with_config` while the user is typing at `cursor`
+/// Missing required properties of the object being edited are not reported yet
+#[instrument(skip(json_schema, file_contents, config), fields(content_len = file_contents.len()))]
+pub fn validate_while_editing(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    config: &Config,
+    cursor: Position,
+) -> ValidationResult<Vec<Diagnostic>> {
+    info!("Starting editing mode schema validation");
+    validate_document(
+        json_schema,
+        file_contents,
+        DocumentValidation {
+            editing_cursor: Some(cursor),
+            ..DocumentValidation::new(config)
+        },
+    )
+}
+
+/// Whether the document parses and satisfies the schema, nothing more
+/// Stops at the first violation and computes no ranges or messages, so it is the
+/// cheapest check; a syntax error is simply invalid
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn is_valid(json_schema: &serde_json::Value, file_contents: &str) -> ValidationResult<bool> {
+    let validator = validation::compile(json_schema)?;
+    let valid = match ParsedContent::new(file_contents)? {
+        ParsedContent::Valid(json) => validator.is_valid(&json),
+        ParsedContent::ParseError(_) => false,
+    };
+
+    debug!(valid, "Checked document validity");
+    Ok(valid)
+}
+
+/// Options of `validate_document`, what sets the `validate_*` entry points apart
+#[derive(Clone, Copy)]
+pub(crate) struct DocumentValidation<'a> {
+    pub config: &'a Config,
+    /// The schema already compiled, see `validation::CompiledSchema`
+    pub compiled: Option<&'a jsonschema::Validator>,
+    /// Id the schema was loaded by, see `Config::schema_id_in_diagnostics`
+    pub schema_id: Option<&'a str>,
+    /// Where the user is typing, see `validate_while_editing`
+    pub editing_cursor: Option<Position>,
+}
+
+impl<'a> DocumentValidation<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            config,
+            compiled: None,
+            schema_id: None,
+            editing_cursor: None,
+        }
+    }
+}
+
+/// The full pipeline behind every `validate_*` entry point
+pub(crate) fn validate_document(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    options: DocumentValidation,
+) -> ValidationResult<Vec<Diagnostic>> {
+    let DocumentValidation {
+        config,
+        compiled,
+        schema_id,
+        editing_cursor,
+    } = options;
+    if let Some(limit) = config.max_document_bytes
+        && file_contents.len() > limit
+    {
+        warn!(
+            content_len = file_contents.len(),
+            limit, "Document exceeds the size limit, skipping validation"
+        );
+        return Ok(vec![parsing::oversized_document_diagnostic(limit)]);
+    }
 
     // Step 1.. Corece filetext as string into JSON content
     // Errors Here are significiant
     let parsed = ParsedContent::new(file_contents)?;
 
     match parsed {
-        ParsedContent::Valid(json) => {
+        ParsedContent::Valid(mut json) => {
+            if config.require_object_root && !json.is_object() {
+                warn!("Document root is not an object");
+                return Ok(vec![parsing::non_object_root_diagnostic(
+                    &json,
+                    file_contents,
+                )]);
+            }
+
+            // a self-describing document is validated against its own schema, without it
+            let embedded_schema = config
+                .embedded_schema_key
+                .as_deref()
+                .and_then(|key| Some((key, json.as_object_mut()?.remove(key)?)));
+            let (json_schema, compiled) = match &embedded_schema {
+                Some((key, embedded)) => {
+                    if let Err(e) = validation::compile(embedded) {
+                        warn!(error = %e, "Embedded schema does not compile");
+                        return Ok(vec![parsing::invalid_embedded_schema_diagnostic(
+                            key,
+                            &e,
+                            file_contents,
+                        )]);
+                    }
+                    debug!(key, "Validating against the embedded schema");
+                    (embedded, None)
+                }
+                None => (json_schema, compiled),
+            };
+
             debug!("JSON parsing successful, proceeding with schema validation");
-            SchemaValidator::new(json_schema, &json, file_contents).validate()
+            // one line index for the validator and the checks after it
+            let document = Document::new(file_contents);
+            let mut validator = SchemaValidator::new(json_schema, &json, file_contents)
+                .with_config(config)
+                .with_document(&document);
+            if let Some(cursor) = editing_cursor {
+                validator = validator.editing_at(cursor);
+            }
+            if let Some(compiled) = compiled {
+                validator = validator.with_validator(compiled);
+            }
+            let mut diagnostics = validator.validate()?;
+
+            diagnostics.extend(validation::precision_loss(&json, &document));
+            diagnostics.extend(validation::deprecated_properties(
+                json_schema,
+                &json,
+                &document,
+            ));
+
+            if config.require_schema_field && parsing::extract_schema_reference(&json).is_none() {
+                debug!("Document does not declare a schema");
+                diagnostics.push(parsing::missing_schema_diagnostic());
+            }
+
+            let tagged_schema = schema_id.filter(|_| config.schema_id_in_diagnostics);
+            if let Some(schema_id) = tagged_schema {
+                validation::tag_schema(&mut diagnostics, schema_id);
+            }
+
+            if config.report_valid_documents && diagnostics.is_empty() {
+                debug!("Document is valid, reporting it");
+                // its message already names the schema, it is not prefixed with it again
+                let mut valid = validation::document_valid_diagnostic(json_schema, schema_id);
+                if let Some(schema_id) = tagged_schema {
+                    validation::record_schema(&mut valid, schema_id);
+                }
+                diagnostics.push(valid);
+            }
+
+            Ok(diagnostics)
         }
         ParsedContent::ParseError(diagnostic) => {
             // Errpr section Handles Json Syntax errors -> from serde_json
-            // needs to be improved to handle sequential, but minor erros.. IE if there is a single fix
-            // suggested, look at that fix and modify file content buffer and then see if it works,
-            // then reparse until either major error without clear solution.
+            // minor errors (missing/trailing commas) are fixed in a copy and reparsed to report
+            // the ones after them, until an error without a clear solution or the bound
+            warn!("JSON parse error detected, returning parse diagnostics");
+            match config.max_parse_recovery_iterations {
+                0 => Ok(vec![*diagnostic]),
+                max_iterations => Ok(parsing::recover_parse_errors(file_contents, max_iterations)),
+            }
+        }
+    }
+}
+
+/// Validates only the value found at `instance_pointer` against `json_schema`
+/// Useful for composite configs where a single section has its own schema
+///
+/// Diagnostic ranges are resolved against the full document, not the subtree,
+/// so they can be published for the whole file as-is
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn validate_subtree(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    instance_pointer: &str,
+) -> ValidationResult<Vec<Diagnostic>> {
+    info!("Starting subtree schema validation");
+    validate_subtree_with(
+        json_schema,
+        &HashMap::new(),
+        file_contents,
+        instance_pointer,
+    )
+}
+
+/// Base URI a schema without an absolute `$id` is registered under by `validate_fragment`
+const FRAGMENT_BASE: &str = "urn:pur:fragment";
+
+/// Validates only the value at `instance_pointer` against the part of `json_schema`
+/// describing it, eg the selection of a huge file
+/// That part is referenced within the full schema, so every `$ref` in it resolves as usual
+///
+/// Values the schema does not describe are not validated
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn validate_fragment(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    instance_pointer: &str,
+) -> ValidationResult<Vec<Diagnostic>> {
+    let Some(reference) = introspection::resolve_reference(json_schema, instance_pointer) else {
+        debug!(
+            pointer = instance_pointer,
+            "No sub-schema describes the fragment"
+        );
+        return Ok(Vec::new());
+    };
+
+    let base = json_schema
+        .get("$id")
+        .and_then(serde_json::Value::as_str)
+        .filter(|id| Url::parse(id).is_ok())
+        .unwrap_or(FRAGMENT_BASE)
+        .trim_end_matches('#');
+    let mut fragment_schema = serde_json::json!({ "$ref": format!("{}{}", base, reference) });
+    if let Some(dialect) = json_schema.get("$schema") {
+        fragment_schema["$schema"] = dialect.clone();
+    }
+    let resources = HashMap::from([(base.to_owned(), Arc::new(json_schema.clone()))]);
+
+    validate_subtree_with(
+        &fragment_schema,
+        &resources,
+        file_contents,
+        instance_pointer,
+    )
+}
+
+/// `validate_subtree` with the schemas `json_schema` references by URI
+fn validate_subtree_with(
+    json_schema: &serde_json::Value,
+    resources: &HashMap<String, Arc<serde_json::Value>>,
+    file_contents: &str,
+    instance_pointer: &str,
+) -> ValidationResult<Vec<Diagnostic>> {
+    match ParsedContent::new(file_contents)? {
+        ParsedContent::Valid(json) => {
+            let subtree = json.pointer(instance_pointer).ok_or_else(|| {
+                SchemaValidationError::JsonPointerResolutionError(instance_pointer.to_string())
+            })?;
+
+            debug!("Subtree resolved, proceeding with schema validation");
+            SchemaValidator::new(json_schema, subtree, file_contents)
+                .with_base_pointer(instance_pointer)
+                .with_resources(resources)
+                .validate()
+        }
+        ParsedContent::ParseError(diagnostic) => {
             warn!("JSON parse error detected, returning parse diagnostic");
-            Ok(vec![diagnostic])
+            Ok(vec![*diagnostic])
+        }
+    }
+}
+
+/// Applies every unambiguous fix (defaults for missing required properties, `const`
+/// corrections, duplicate key removal) and revalidates the result
+///
+/// Returns the fixed text along with the diagnostics that could not be fixed
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn autofix(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+) -> ValidationResult<(String, Vec<Diagnostic>)> {
+    let json = match ParsedContent::new(file_contents)? {
+        ParsedContent::Valid(json) => json,
+        ParsedContent::ParseError(diagnostic) => {
+            warn!("JSON parse error detected, nothing can be fixed");
+            return Ok((file_contents.to_owned(), vec![*diagnostic]));
+        }
+    };
+
+    let fixes = code_action::safe_fixes(json_schema, &json, file_contents)?;
+    info!(fix_count = fixes.len(), "Applying safe fixes");
+
+    let fixed = code_action::apply(file_contents, &fixes);
+    let diagnostics = validate_liberally(json_schema, &fixed)?;
+
+    Ok((fixed, diagnostics))
+}
+
+/// Decompresses a gzipped document, then validates it like `validate_liberally`
+/// Diagnostic ranges refer to the decompressed text
+#[cfg(feature = "gzip")]
+#[instrument(skip(json_schema, compressed), fields(compressed_len = compressed.len()))]
+pub fn validate_gzip(
+    json_schema: &serde_json::Value,
+    compressed: &[u8],
+) -> ValidationResult<Vec<Diagnostic>> {
+    use std::io::Read;
+
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(compressed)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| SchemaValidationError::DecompressionError(e.to_string()))?;
+
+    let file_contents = String::from_utf8(decompressed)?;
+    debug!(
+        content_len = file_contents.len(),
+        "Decompressed gzip payload"
+    );
+
+    validate_liberally(json_schema, &file_contents)
+}
+
+/// Same as `validate_with_config`, with the diagnostics bucketed by severity, errors first
+/// Diagnostics without a severity count as errors, like the command line output
+#[instrument(skip(json_schema, file_contents, config), fields(content_len = file_contents.len()))]
+pub fn validate_grouped(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    config: &Config,
+) -> ValidationResult<BTreeMap<DiagnosticSeverity, Vec<Diagnostic>>> {
+    let mut grouped: BTreeMap<DiagnosticSeverity, Vec<Diagnostic>> = BTreeMap::new();
+    for diagnostic in validate_with_config(json_schema, file_contents, config)? {
+        let severity = diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR);
+        grouped.entry(severity).or_default().push(diagnostic);
+    }
+
+    debug!(
+        severity_count = grouped.len(),
+        "Grouped diagnostics by severity"
+    );
+    Ok(grouped)
+}
+
+/// Validates the document against each of several independent schemas, eg a base schema
+/// plus an organization policy, like `validate_liberally`
+/// Every schema is compiled once up front, so a broken one fails before any validation;
+/// diagnostics reported by more than one schema (eg a syntax error) are kept once
+#[instrument(skip(json_schemas, file_contents), fields(schema_count = json_schemas.len()))]
+pub fn validate_all_of_schemas(
+    json_schemas: &[serde_json::Value],
+    file_contents: &str,
+) -> ValidationResult<Vec<Diagnostic>> {
+    let compiled = json_schemas
+        .iter()
+        .map(|json_schema| validation::compile(json_schema).map(|v| (json_schema, v)))
+        .collect::<ValidationResult<Vec<_>>>()?;
+
+    let mut merged: Vec<Diagnostic> = Vec::new();
+    for (json_schema, validator) in &compiled {
+        let diagnostics = validate_document(
+            json_schema,
+            file_contents,
+            DocumentValidation {
+                compiled: Some(validator),
+                ..DocumentValidation::new(Config::default_ref())
+            },
+        )?;
+        for diagnostic in diagnostics {
+            if !merged.contains(&diagnostic) {
+                merged.push(diagnostic);
+            }
+        }
+    }
+
+    debug!(count = merged.len(), "Merged diagnostics of all schemas");
+    Ok(merged)
+}
+
+/// Validates every ```json code fence of a Markdown document like `validate_liberally`
+/// Diagnostic ranges are shifted onto the fence's lines, so they refer to the Markdown text
+#[instrument(skip(json_schema, markdown), fields(markdown_len = markdown.len()))]
+pub fn validate_markdown(
+    json_schema: &serde_json::Value,
+    markdown: &str,
+) -> ValidationResult<Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+
+    for fence in markdown::json_fences(markdown) {
+        let mut fence_diagnostics = validate_liberally(json_schema, &fence.contents)?;
+        debug!(
+            start_line = fence.start_line,
+            diagnostic_count = fence_diagnostics.len(),
+            "Validated json fence"
+        );
+
+        for diagnostic in &mut fence_diagnostics {
+            diagnostic.range.start.line += fence.start_line;
+            diagnostic.range.end.line += fence.start_line;
+        }
+        diagnostics.extend(fence_diagnostics);
+    }
+
+    Ok(diagnostics)
+}
+
+/// Every unambiguous fix `autofix` would apply, as one edit of the document at `uri`
+/// for a "fix all" action. Overlapping fixes are dropped, the remaining text edits are
+/// ordered last in the document first so applying them one by one stays correct
+///
+/// A document with a syntax error gets an empty edit
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn auto_fix_edits(
+    json_schema: &serde_json::Value,
+    uri: &Url,
+    file_contents: &str,
+) -> ValidationResult<WorkspaceEdit> {
+    let ParsedContent::Valid(json) = ParsedContent::new(file_contents)? else {
+        warn!("JSON parse error detected, nothing can be fixed");
+        return Ok(WorkspaceEdit::default());
+    };
+
+    let fixes = code_action::safe_fixes(json_schema, &json, file_contents)?;
+    let document = Document::new(file_contents);
+    let edits: Vec<TextEdit> = code_action::non_overlapping(&fixes)
+        .into_iter()
+        .map(|fix| fix.to_text_edit(&document))
+        .collect();
+    info!(edit_count = edits.len(), "Batched safe fixes");
+
+    Ok(WorkspaceEdit {
+        changes: Some(HashMap::from([(uri.clone(), edits)])),
+        ..Default::default()
+    })
+}
+
+/// Ranges of every violation of the schema `keyword` (eg `additionalProperties`) in the document
+/// Each unexpected key gets its own range, so a "fix all" can visit them one by one
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn findings_by_keyword(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+    keyword: &str,
+) -> ValidationResult<Vec<Range>> {
+    let json: serde_json::Value = serde_json::from_str(file_contents)?;
+    let validator = validation::compile(json_schema)?;
+    let document = Document::new(file_contents);
+
+    let mut ranges = Vec::new();
+    let mut false_schema_objects = std::collections::HashSet::new();
+    for error in validator.iter_errors(&json) {
+        if validation::ValidationKind::from_error(&error).keyword() != keyword {
+            continue;
+        }
+
+        match error.kind() {
+            jsonschema::error::ValidationErrorKind::AdditionalProperties { unexpected } => {
+                let object_pointer = validation::canonical_pointer(error.instance_path());
+                ranges.extend(unexpected.iter().filter_map(|key| {
+                    let key_pointer = format!("{}/{}", object_pointer, cursor::escape(key));
+                    diagnostic_range::try_guarded(&key_pointer, || {
+                        json_pointer::key_range_in(&key_pointer, &document)
+                    })
+                }));
+            }
+            // without sibling `properties` jsonschema fails each extra value against the false
+            // subschema but reports the parent object's path, so take the keys from the document
+            jsonschema::error::ValidationErrorKind::FalseSchema => {
+                let object_pointer = validation::canonical_pointer(error.instance_path());
+                if !false_schema_objects.insert(object_pointer.clone()) {
+                    continue;
+                }
+                let keys = json
+                    .pointer(&object_pointer)
+                    .and_then(serde_json::Value::as_object)
+                    .into_iter()
+                    .flat_map(|object| object.keys());
+                ranges.extend(keys.filter_map(|key| {
+                    let key_pointer = format!("{}/{}", object_pointer, cursor::escape(key));
+                    diagnostic_range::try_guarded(&key_pointer, || {
+                        json_pointer::key_range_in(&key_pointer, &document)
+                    })
+                }));
+            }
+            _ => {
+                let diagnostic =
+                    validation::ValidationDiagnostic::new(&error, json_schema, "", &document);
+                ranges.push(validation::ValidationFinding::from(diagnostic).range);
+            }
+        }
+    }
+
+    debug!(range_count = ranges.len(), "Collected keyword violations");
+    Ok(ranges)
+}
+
+/// Largest document `validate_async_read` accepts, in bytes
+pub const MAX_ASYNC_READ_BYTES: usize = 16 * 1024 * 1024;
+
+/// Reads a whole document from `reader`, then validates it like `validate_liberally`
+/// Documents larger than `MAX_ASYNC_READ_BYTES` are rejected with `DocumentTooLarge`
+pub async fn validate_async_read<R>(
+    json_schema: &serde_json::Value,
+    reader: R,
+) -> ValidationResult<Vec<Diagnostic>>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    validate_async_read_with_limit(json_schema, reader, MAX_ASYNC_READ_BYTES).await
+}
+
+/// Same as `validate_async_read`, with a custom size limit in bytes
+#[instrument(skip(json_schema, reader))]
+pub async fn validate_async_read_with_limit<R>(
+    json_schema: &serde_json::Value,
+    reader: R,
+    limit: usize,
+) -> ValidationResult<Vec<Diagnostic>>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+
+    // read one byte past the limit, so an exactly sized document is still accepted
+    let mut buffer = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut buffer)
+        .await?;
+    if buffer.len() > limit {
+        warn!("Document exceeds the size limit");
+        return Err(SchemaValidationError::DocumentTooLarge { limit });
+    }
+
+    let file_contents = String::from_utf8(buffer)?;
+    debug!(content_len = file_contents.len(), "Read document");
+
+    validate_liberally(json_schema, &file_contents)
+}
+
+/// Lists, per object pointer, the required properties missing from the document
+/// Objects are sorted by pointer, so parents come before their children
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn missing_required(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+) -> ValidationResult<Vec<(String, Vec<String>)>> {
+    let json: serde_json::Value = serde_json::from_str(file_contents)?;
+    let validator = validation::compile(json_schema)?;
+
+    let mut missing: Vec<(String, Vec<String>)> = Vec::new();
+    for error in validator.iter_errors(&json) {
+        let jsonschema::error::ValidationErrorKind::Required { property } = error.kind() else {
+            continue;
+        };
+        let Some(property) = property.as_str() else {
+            continue;
+        };
+
+        let pointer = validation::canonical_pointer(error.instance_path());
+        match missing.iter_mut().find(|(p, _)| *p == pointer) {
+            Some((_, properties)) => properties.push(property.to_string()),
+            None => missing.push((pointer, vec![property.to_string()])),
         }
     }
+
+    missing.sort_by(|a, b| a.0.cmp(&b.0));
+    debug!(
+        object_count = missing.len(),
+        "Collected missing required properties"
+    );
+    Ok(missing)
+}
+
+/// RFC 6901 instance pointers of every violation, in validation order, for callers mapping
+/// them to positions themselves. Nothing is resolved against the text
+#[instrument(skip(json_schema, file_contents), fields(content_len = file_contents.len()))]
+pub fn error_pointers(
+    json_schema: &serde_json::Value,
+    file_contents: &str,
+) -> ValidationResult<Vec<String>> {
+    let json: serde_json::Value = serde_json::from_str(file_contents)?;
+    let validator = validation::compile(json_schema)?;
+
+    let pointers: Vec<String> = validator
+        .iter_errors(&json)
+        .map(|error| validation::canonical_pointer(error.instance_path()))
+        .collect();
+    debug!(error_count = pointers.len(), "Collected error pointers");
+    Ok(pointers)
+}
+
+/// `enum` options of the schema node governing the value under `position`, eg to offer a dropdown
+/// None when the cursor is on a key, or the value is not restricted to an enum
+#[instrument(skip(file_contents, json_schema), fields(line = position.line, character = position.character))]
+pub fn enum_options_at(
+    position: Position,
+    file_contents: &str,
+    json_schema: &serde_json::Value,
+) -> Option<Vec<serde_json::Value>> {
+    let context = cursor::context_at(file_contents, position)?;
+    if context.location != cursor::CursorLocation::Value {
+        return None;
+    }
+
+    introspection::resolve(json_schema, &context.pointer)?
+        .get("enum")?
+        .as_array()
+        .cloned()
+}
+
+/// Known-good document for the embedded service schema
+const SELF_TEST_SAMPLE: &str = include_str!("../schemas/service.sample.json");
+
+/// Compiles the embedded service schema and validates a known-good sample against it
+/// Catches shipping a broken embedded schema before any document is opened
+#[instrument]
+pub async fn self_test() -> ValidationResult<()> {
+    let json_schema = resolver::DefaultSchemaResolver::default()
+        .resolve("service.schema")
+        .await?;
+    let validator = validation::compile(&json_schema)?;
+
+    let sample: serde_json::Value = serde_json::from_str(SELF_TEST_SAMPLE)?;
+    let error_count = validator.iter_errors(&sample).count();
+    if error_count > 0 {
+        warn!(
+            error_count,
+            "Embedded sample does not satisfy the embedded schema"
+        );
+        return Err(SchemaValidationError::ValidationFailed(error_count));
+    }
+
+    info!("Self-test passed");
+    Ok(())
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
     use crate::json_pointer;
+    use crate::test_util::{assert_diagnostic_at, assert_no_diagnostics};
     use std::fs::File;
     use std::io::BufReader;
 
@@ -133,6 +802,18 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_is_valid_matches_fixtures() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = TestSchema::new()?;
+
+        assert!(is_valid(&schema.json_schema, VALID_JSON)?);
+        // JSON_WITH_SCHEMA_ERROR happens to satisfy the service schema, this one does not
+        let schema_error = include_str!("../testing/test_pattern_mismatch.json");
+        assert!(!is_valid(&schema.json_schema, schema_error)?);
+        assert!(!is_valid(&schema.json_schema, INVALID_JSON_SYNTAX)?);
+        Ok(())
+    }
+
     #[test]
     fn test_json_syntax_error_produces_diagnostic() -> Result<(), Box<dyn std::error::Error>> {
         let schema = TestSchema::new().unwrap_or_else(|_| TestSchema::minimal());
@@ -173,6 +854,246 @@ pub mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_subtree_validation_maps_ranges_to_full_document()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let runtime_schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "docker": {
+                    "type": "object",
+                    "properties": {
+                        "tag": { "type": "integer" }
+                    }
+                }
+            }
+        });
+
+        let diagnostics = validate_subtree(&runtime_schema, VALID_JSON, "/runtime")?;
+
+        assert_eq!(diagnostics.len(), 1, "Expected a single type error for tag");
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.source.as_deref(), Some("/runtime/docker/tag"));
+
+        let tag_line = VALID_JSON
+            .lines()
+            .position(|line| line.contains("\"tag\""))
+            .expect("fixture contains tag") as u32;
+        assert_eq!(
+            diagnostic.range.start.line, tag_line,
+            "Range should point at tag within the full document"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subtree_validation_unknown_pointer_errors() {
+        let schema = TestSchema::minimal();
+        let result = validate_subtree(&schema.json_schema, VALID_JSON, "/missing");
+
+        assert!(matches!(
+            result,
+            Err(SchemaValidationError::JsonPointerResolutionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_fragment_refs_resolve_within_full_schema() {
+        let schema = serde_json::json!({
+            "$id": "https://example.com/service.schema.json",
+            "properties": {
+                "api": {
+                    "properties": {
+                        "port": { "type": "integer" },
+                        "admin port": { "$ref": "#/properties/api/properties/port" }
+                    }
+                },
+                "admin": { "$ref": "#/properties/api" }
+            }
+        });
+        let contents = r#"{ "api": { "port": 1 }, "admin": { "port": "81", "admin port": "82" } }"#;
+
+        let diagnostics = validate_fragment(&schema, contents, "/admin").unwrap();
+
+        let mut sources: Vec<_> = diagnostics
+            .iter()
+            .filter_map(|d| d.source.as_deref())
+            .collect();
+        sources.sort();
+        assert_eq!(sources, vec!["/admin/admin port", "/admin/port"]);
+        assert!(
+            validate_fragment(&schema, contents, "/api")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_auto_fix_edits_batch_every_safe_fix() {
+        let schema = serde_json::json!({
+            "required": ["tag"],
+            "properties": {
+                "kind": { "const": "service" },
+                "tag": { "type": "string", "default": "latest" }
+            }
+        });
+        let uri = Url::parse("file:///service.json").unwrap();
+        let contents =
+            "{\n  \"kind\": \"servce\",\n  \"image\": \"nginx\",\n  \"image\": \"alpine\"\n}";
+
+        let edit = auto_fix_edits(&schema, &uri, contents).unwrap();
+        let edits = &edit.changes.unwrap()[&uri];
+
+        assert_eq!(edits.len(), 3);
+        assert!(
+            edits
+                .windows(2)
+                .all(|pair| pair[1].range.end <= pair[0].range.start)
+        );
+        // in order, every edit's range still refers to the text before it
+        let mut fixed = contents.to_owned();
+        for edit in edits {
+            let (start, end) = json_pointer::range_to_byte_span(&edit.range, &fixed).unwrap();
+            fixed.replace_range(start..end, &edit.new_text);
+        }
+        assert_eq!(
+            fixed,
+            "{\n  \"kind\": \"service\",\n  \"image\": \"alpine\",\n  \"tag\": \"latest\"\n}"
+        );
+        assert_eq!(fixed, autofix(&schema, contents).unwrap().0);
+
+        let overlapping = [(0, 4), (2, 6), (6, 8)].map(|span| code_action::Fix {
+            title: format!("{:?}", span),
+            span,
+            new_text: String::new(),
+        });
+        let kept: Vec<_> = code_action::non_overlapping(&overlapping)
+            .into_iter()
+            .map(|fix| fix.span)
+            .collect();
+        assert_eq!(kept, vec![(6, 8), (2, 6)]);
+    }
+
+    #[test]
+    fn test_autofix_inserts_missing_default() -> Result<(), Box<dyn std::error::Error>> {
+        let docker_schema = serde_json::json!({
+            "type": "object",
+            "required": ["image", "tag"],
+            "properties": {
+                "image": { "type": "string" },
+                "tag": { "type": "string", "default": "latest" }
+            }
+        });
+        let contents = "{\n  \"image\": \"nginx\"\n}";
+
+        let (fixed, diagnostics) = autofix(&docker_schema, contents)?;
+
+        assert_eq!(
+            fixed,
+            "{\n  \"image\": \"nginx\",\n  \"tag\": \"latest\"\n}"
+        );
+        assert_no_diagnostics(&diagnostics);
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    fn gzip(contents: &str) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(contents.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_round_trip_validation() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = TestSchema::new()?;
+
+        let valid = validate_gzip(&schema.json_schema, &gzip(VALID_JSON))?;
+        assert!(valid.is_empty(), "Valid gzipped document should pass");
+
+        let invalid = validate_gzip(&schema.json_schema, &gzip(INVALID_JSON_SYNTAX))?;
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(
+            invalid[0].range.start.line, 3,
+            "Range should refer to the decompressed text"
+        );
+
+        assert!(matches!(
+            validate_gzip(&schema.json_schema, b"not gzip"),
+            Err(SchemaValidationError::DecompressionError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_required_groups_by_object() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = TestSchema::new()?;
+
+        // every required property is present in this fixture (ports is optional)
+        assert!(missing_required(&schema.json_schema, JSON_WITH_SCHEMA_ERROR)?.is_empty());
+
+        let contents = r#"{ "version": "1.2.3", "runtime": { "docker": {} } }"#;
+        let missing = missing_required(&schema.json_schema, contents)?;
+
+        assert_eq!(
+            missing,
+            vec![
+                ("".to_string(), vec!["service".to_string()]),
+                ("/runtime".to_string(), vec!["type".to_string()]),
+                ("/runtime/docker".to_string(), vec!["image".to_string()]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_pointers_are_raw_instance_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = TestSchema::new()?;
+        assert!(error_pointers(&schema.json_schema, VALID_JSON)?.is_empty());
+
+        let contents = r#"{ "service": "api", "version": "1.2.3", "runtime": { "type": "vm" } }"#;
+        let pointers = error_pointers(&schema.json_schema, contents)?;
+
+        assert_eq!(pointers, vec!["/runtime/type".to_string()]);
+
+        // a missing property is reported at the object that lacks it, once per failing keyword
+        let contents = r#"{ "service": "api", "version": "1.2.3", "runtime": {} }"#;
+        let pointers = error_pointers(&schema.json_schema, contents)?;
+        assert!(!pointers.is_empty());
+        assert!(pointers.iter().all(|pointer| pointer == "/runtime"));
+        assert!(error_pointers(&schema.json_schema, INVALID_JSON_SYNTAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_enum_options_at_value_cursor() -> Result<(), Box<dyn std::error::Error>> {
+        let schema = TestSchema::new()?;
+
+        // inside the "docker" value of runtime.type
+        let options = enum_options_at(Position::new(5, 14), VALID_JSON, &schema.json_schema);
+        assert_eq!(
+            options,
+            Some(vec![
+                serde_json::json!("docker"),
+                serde_json::json!("native")
+            ])
+        );
+
+        // on the key itself, and on a value without an enum
+        assert_eq!(
+            enum_options_at(Position::new(5, 6), VALID_JSON, &schema.json_schema),
+            None
+        );
+        assert_eq!(
+            enum_options_at(Position::new(2, 15), VALID_JSON, &schema.json_schema),
+            None
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_json_pointer_resolution() {
         let test_json = r#"{
@@ -256,4 +1177,430 @@ pub mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_self_test_passes_for_embedded_schema() {
+        self_test().await.expect("embedded schema and sample agree");
+    }
+
+    #[tokio::test]
+    async fn test_async_read_validates_and_enforces_size_limit() {
+        let schema = TestSchema::new().unwrap_or_else(|_| TestSchema::minimal());
+
+        let diagnostics = validate_async_read(&schema.json_schema, VALID_JSON.as_bytes())
+            .await
+            .unwrap();
+        assert!(diagnostics.is_empty());
+
+        let result =
+            validate_async_read_with_limit(&schema.json_schema, VALID_JSON.as_bytes(), 16).await;
+        assert!(matches!(
+            result,
+            Err(SchemaValidationError::DocumentTooLarge { limit: 16 })
+        ));
+    }
+
+    #[test]
+    fn test_required_schema_field_warns_only_when_absent() {
+        let schema = TestSchema::new().unwrap_or_else(|_| TestSchema::minimal());
+        let config = Config {
+            require_schema_field: true,
+            ..Default::default()
+        };
+        let without_schema = VALID_JSON.replace("\"$schema\": \"here\",", "");
+
+        let present = validate_with_config(&schema.json_schema, VALID_JSON, &config).unwrap();
+        let absent = validate_with_config(&schema.json_schema, &without_schema, &config).unwrap();
+
+        assert_no_diagnostics(&present);
+        assert_eq!(
+            render::diagnostics_to_snapshot(&absent),
+            serde_json::json!([{
+                "line": 0,
+                "character": 0,
+                "severity": "warning",
+                "code": null,
+                "message": "Document does not declare a $schema"
+            }])
+        );
+    }
+
+    #[test]
+    fn test_require_object_root_rejects_root_array() {
+        let schema = serde_json::json!({ "type": ["object", "array"] });
+        let config = Config {
+            require_object_root: true,
+            ..Default::default()
+        };
+        let contents = "[\n  { \"name\": \"api\" }\n]";
+
+        let diagnostics = validate_with_config(&schema, contents, &config).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].severity,
+            Some(tower_lsp::lsp_types::DiagnosticSeverity::ERROR)
+        );
+        assert!(diagnostics[0].message.contains("array"));
+        assert_eq!(diagnostics[0].range.end.line, 2);
+
+        // the schema alone accepts the array
+        assert!(validate_liberally(&schema, contents).unwrap().is_empty());
+        assert!(
+            validate_with_config(&schema, "{}", &config)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_docs_link_set_when_base_url_configured() {
+        let schema = serde_json::json!({
+            "properties": { "port": { "type": "integer" } }
+        });
+        let contents = r#"{ "port": "80" }"#;
+        let config = Config {
+            docs_base_url: Some("https://docs.example.com/service".to_string()),
+            ..Default::default()
+        };
+
+        let linked = validate_with_config(&schema, contents, &config).unwrap();
+        let plain = validate_liberally(&schema, contents).unwrap();
+
+        assert_eq!(
+            linked[0].code_description.as_ref().unwrap().href.as_str(),
+            "https://docs.example.com/service#/properties/port/type"
+        );
+        assert_eq!(
+            linked[0].code,
+            Some(tower_lsp::lsp_types::NumberOrString::String("type".into()))
+        );
+        assert_eq!(plain[0].code_description, None);
+        assert_eq!(plain[0].code, None);
+    }
+
+    #[test]
+    fn test_schema_id_tags_diagnostics_when_enabled() {
+        let schema = serde_json::json!({
+            "properties": { "port": { "type": "integer" } }
+        });
+        let contents = "{\n  \"port\": \"80\"\n}";
+        let config = Config {
+            schema_id_in_diagnostics: true,
+            ..Default::default()
+        };
+
+        let tagged = validate_with_schema_id(&schema, "service.schema", contents, &config).unwrap();
+        let untagged =
+            validate_with_schema_id(&schema, "service.schema", contents, Config::default_ref())
+                .unwrap();
+
+        assert_eq!(tagged.len(), 1);
+        assert!(tagged[0].message.starts_with("service.schema: "));
+        assert_eq!(
+            tagged[0].data,
+            Some(serde_json::json!({ "schema": "service.schema" }))
+        );
+        assert_diagnostic_at(&tagged, 1, "port");
+        assert!(!untagged[0].message.contains("service.schema"));
+        assert_eq!(untagged[0].data, None);
+
+        // the valid document message names the schema once
+        let config = Config {
+            report_valid_documents: true,
+            ..config
+        };
+        let valid =
+            validate_with_schema_id(&schema, "service.schema", r#"{ "port": 80 }"#, &config)
+                .unwrap();
+        assert_eq!(valid[0].message, "Document valid against service.schema");
+        assert_eq!(
+            valid[0].data,
+            Some(serde_json::json!({ "schema": "service.schema" }))
+        );
+    }
+
+    #[test]
+    fn test_parse_recovery_follows_configured_bound() {
+        let schema = serde_json::json!({ "type": "object" });
+        let contents = "{\n  \"a\": 1\n  \"b\": 2\n  \"c\": 3\n}";
+        let config = |max_parse_recovery_iterations| Config {
+            max_parse_recovery_iterations,
+            ..Default::default()
+        };
+
+        // recovery is on by default
+        assert_eq!(validate_liberally(&schema, contents).unwrap().len(), 2);
+        assert_eq!(
+            validate_with_config(&schema, contents, &config(10))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            validate_with_config(&schema, contents, &config(1))
+                .unwrap()
+                .len(),
+            2
+        );
+        let first_only = validate_with_config(&schema, contents, &config(0)).unwrap();
+        assert_eq!(first_only.len(), 1);
+        assert_diagnostic_at(&first_only, 2, "expected `,`");
+    }
+
+    #[test]
+    fn test_embedded_schema_validates_rest_of_document() {
+        let outer = serde_json::json!({ "required": ["unrelated"] });
+        let contents = r#"{
+  "__schema": {
+    "properties": { "port": { "type": "integer" } },
+    "required": ["port"]
+  },
+  "port": "80"
+}"#;
+        let config = Config {
+            embedded_schema_key: Some("__schema".to_string()),
+            ..Default::default()
+        };
+
+        let diagnostics = validate_with_config(&outer, contents, &config).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_diagnostic_at(&diagnostics, 5, "integer");
+
+        // without the key configured the outer schema applies
+        let diagnostics = validate_liberally(&outer, contents).unwrap();
+        assert_diagnostic_at(&diagnostics, 0, "unrelated");
+
+        let broken = r#"{ "__schema": { "type": 12 }, "port": 80 }"#;
+        let diagnostics = validate_with_config(&outer, broken, &config).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("__schema"));
+        assert_eq!(diagnostics[0].range.start.character, 2);
+    }
+
+    #[test]
+    fn test_schema_declaration_range_finds_schema_key() {
+        let range = parsing::schema_declaration_range(VALID_JSON).unwrap();
+
+        assert_eq!(range.start, Position::new(1, 2));
+        assert_eq!(range.end, Position::new(1, 11));
+        let without_schema = VALID_JSON.replace("\"$schema\": \"here\",", "");
+        assert_eq!(parsing::schema_declaration_range(&without_schema), None);
+    }
+
+    #[test]
+    fn test_oversized_document_is_skipped_with_warning() {
+        let schema = serde_json::json!({ "required": ["service"] });
+        let config = Config {
+            max_document_bytes: Some(16),
+            ..Default::default()
+        };
+        // broken and invalid, neither is reported once the size check skips it
+        let contents = r#"{ "port": 8080 "name": "api" }"#;
+
+        let diagnostics = validate_with_config(&schema, contents, &config).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diagnostics[0].message,
+            "Document exceeds the size limit of 16 bytes, not validated"
+        );
+        // documents within the limit are still validated
+        let small = validate_with_config(&schema, "{}", &config).unwrap();
+        assert!(small[0].message.contains("service"));
+    }
+
+    #[test]
+    fn test_valid_document_reported_only_when_enabled() {
+        let schema = serde_json::json!({
+            "title": "Service",
+            "properties": { "port": { "type": "integer" } }
+        });
+        let config = Config {
+            report_valid_documents: true,
+            ..Default::default()
+        };
+
+        let reported = validate_with_config(&schema, r#"{ "port": 80 }"#, &config).unwrap();
+        assert_eq!(reported.len(), 1);
+        assert_eq!(reported[0].severity, Some(DiagnosticSeverity::INFORMATION));
+        assert_eq!(reported[0].message, "Document valid against Service");
+
+        let named =
+            validate_with_schema_id(&schema, "service.schema", r#"{ "port": 80 }"#, &config)
+                .unwrap();
+        assert_eq!(named[0].message, "Document valid against service.schema");
+
+        assert_no_diagnostics(&validate_liberally(&schema, r#"{ "port": 80 }"#).unwrap());
+        // an invalid document gets its errors only
+        let invalid = validate_with_config(&schema, r#"{ "port": "80" }"#, &config).unwrap();
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn test_grouped_diagnostics_split_errors_and_warnings() {
+        let schema = serde_json::json!({
+            "required": ["name"],
+            "properties": { "port": { "type": "integer" } }
+        });
+        let config = Config {
+            require_schema_field: true,
+            report_valid_documents: true,
+            ..Default::default()
+        };
+
+        let grouped = validate_grouped(&schema, r#"{ "port": "80" }"#, &config).unwrap();
+
+        assert_eq!(
+            grouped.keys().copied().collect::<Vec<_>>(),
+            vec![DiagnosticSeverity::ERROR, DiagnosticSeverity::WARNING]
+        );
+        assert_eq!(grouped[&DiagnosticSeverity::ERROR].len(), 2);
+        assert_eq!(grouped[&DiagnosticSeverity::WARNING].len(), 1);
+        assert!(
+            grouped[&DiagnosticSeverity::WARNING][0]
+                .message
+                .contains("$schema")
+        );
+
+        let valid = r#"{ "$schema": "service.schema", "name": "api" }"#;
+        let grouped = validate_grouped(&schema, valid, &config).unwrap();
+        assert_eq!(
+            grouped.keys().copied().collect::<Vec<_>>(),
+            vec![DiagnosticSeverity::INFORMATION]
+        );
+        // the default config reports neither the valid document nor the missing field
+        assert!(
+            validate_grouped(&schema, valid, Config::default_ref())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            !validate_grouped(&schema, r#"{ "name": "api" }"#, Config::default_ref())
+                .unwrap()
+                .contains_key(&DiagnosticSeverity::WARNING)
+        );
+    }
+
+    #[test]
+    fn test_all_of_schemas_merges_failing_schema_diagnostics() {
+        let base = serde_json::json!({
+            "required": ["service"],
+            "properties": { "port": { "type": "integer" } }
+        });
+        let policy = serde_json::json!({
+            "required": ["owner"],
+            "properties": { "port": { "type": "integer" } }
+        });
+        let contents = r#"{ "service": "api", "port": 8080 }"#;
+
+        let diagnostics =
+            validate_all_of_schemas(&[base.clone(), policy.clone()], contents).unwrap();
+
+        assert_eq!(diagnostics, validate_liberally(&policy, contents).unwrap());
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("owner"));
+
+        // the same syntax error from both schemas is reported once
+        let broken = validate_all_of_schemas(&[base, policy], "{ \"port\": }").unwrap();
+        assert_eq!(broken.len(), 1);
+    }
+
+    #[test]
+    fn test_markdown_diagnostics_map_to_fence_lines() {
+        let schema = serde_json::json!({
+            "properties": { "port": { "type": "integer" } }
+        });
+        let markdown = r#"# Service
+
+A valid example:
+
+```json
+{ "port": 80 }
+```
+
+An invalid one:
+
+```json
+{
+  "port": "80"
+}
+```
+"#;
+
+        let diagnostics = validate_markdown(&schema, markdown).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_diagnostic_at(&diagnostics, 12, "integer");
+        assert!(markdown.lines().nth(12).unwrap().contains(r#""port""#));
+    }
+
+    #[test]
+    fn test_findings_by_keyword_covers_every_extra_key() {
+        let schema = serde_json::json!({
+            "additionalProperties": false,
+            "properties": {
+                "service": { "type": "string" },
+                "runtime": { "type": "object", "additionalProperties": false }
+            }
+        });
+        let contents = r#"{
+  "service": "api",
+  "servce": "typo",
+  "runtime": {
+    "imgae": "nginx"
+  },
+  "extra": true
+}"#;
+
+        let mut lines: Vec<u32> = findings_by_keyword(&schema, contents, "additionalProperties")
+            .unwrap()
+            .iter()
+            .map(|range| range.start.line)
+            .collect();
+        lines.sort();
+
+        assert_eq!(lines, vec![2, 4, 6]);
+        assert!(
+            findings_by_keyword(&schema, contents, "required")
+                .unwrap()
+                .is_empty()
+        );
+    }
+    #[test]
+    fn test_large_document_diagnostics_match_scanned_ranges() {
+        let schema = serde_json::json!({
+            "properties": { "key_0": { "deprecated": true } },
+            "additionalProperties": { "type": "string" }
+        });
+        // every 500th value is a number, one of them too large for an i64
+        let members: Vec<String> = (0..40_000)
+            .map(|idx| match idx {
+                39_500 => format!("  \"key_{}\": 98765432109876543210", idx),
+                idx if idx % 500 == 0 && idx > 0 => format!("  \"key_{}\": {}", idx, idx),
+                idx => format!("  \"key_{}\": \"caf\u{e9} {}\"", idx, idx),
+            })
+            .collect();
+        let contents = format!("{{\n{}\n}}", members.join(",\n"));
+        assert!(contents.len() >= document::ROPE_THRESHOLD);
+
+        let diagnostics = validate_liberally(&schema, &contents).unwrap();
+
+        // 79 type errors, the deprecated key and the precision warning
+        assert_eq!(diagnostics.len(), 81);
+        for diagnostic in &diagnostics {
+            let pointer = diagnostic.source.as_deref().unwrap();
+            let scanned = if diagnostic.severity == Some(DiagnosticSeverity::ERROR) {
+                json_pointer::into_range(pointer, &contents)
+            } else if diagnostic.tags.is_some() {
+                json_pointer::key_range(pointer, &contents)
+            } else {
+                json_pointer::value_range(pointer, &contents)
+            };
+            assert_eq!(Some(diagnostic.range), scanned, "{}", pointer);
+        }
+    }
 }
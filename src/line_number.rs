@@ -1,19 +1,145 @@
-use tracing::{instrument, trace};
+use tower_lsp::lsp_types::{Position, PositionEncodingKind};
+use tracing::instrument;
 
+use crate::document::Document;
+
+/// Unit the `character` of an LSP position counts in, negotiated during `initialize`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// bytes of the UTF-8 encoded line
+    Utf8,
+    /// UTF-16 code units, the LSP default
+    #[default]
+    Utf16,
+}
+
+impl PositionEncoding {
+    /// First encoding of the client's preference list the server supports,
+    /// UTF-16 when the client offers none (mandatory for every client)
+    pub fn negotiate(offered: Option<&[PositionEncodingKind]>) -> Self {
+        offered
+            .unwrap_or_default()
+            .iter()
+            .find_map(|kind| match kind.as_str() {
+                "utf-8" => Some(Self::Utf8),
+                "utf-16" => Some(Self::Utf16),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+        }
+    }
+
+    pub(crate) fn width(self, ch: char) -> usize {
+        match self {
+            Self::Utf8 => ch.len_utf8(),
+            Self::Utf16 => ch.len_utf16(),
+        }
+    }
+}
+
+/// Converts an LSP line/character position to a byte index
+/// Characters are counted in UTF-16 code units per the LSP default encoding
+///
+/// Returns None when the line does not exist, positions past a line end clamp to it
+#[instrument(skip(raw_file_contents))]
+pub(crate) fn to_index(raw_file_contents: &str, line: u32, character: u32) -> Option<usize> {
+    to_index_in(raw_file_contents, line, character, PositionEncoding::Utf16)
+}
+
+/// `to_index` counting characters in `encoding`
 #[instrument(skip(raw_file_contents))]
-pub(crate) fn from_index(raw_file_contents: &str, index: usize) -> u32 {
-    let safe_index = index.min(raw_file_contents.len());
+pub fn to_index_in(
+    raw_file_contents: &str,
+    line: u32,
+    character: u32,
+    encoding: PositionEncoding,
+) -> Option<usize> {
+    Document::Text(raw_file_contents).to_index(line, character, encoding)
+}
+
+/// Converts a byte index to an LSP position, counting characters in UTF-16 code units
+#[instrument(skip(raw_file_contents))]
+pub(crate) fn to_position(raw_file_contents: &str, index: usize) -> Position {
+    to_position_in(raw_file_contents, index, PositionEncoding::Utf16)
+}
+
+/// `to_position` counting characters in `encoding`
+#[instrument(skip(raw_file_contents))]
+pub fn to_position_in(
+    raw_file_contents: &str,
+    index: usize,
+    encoding: PositionEncoding,
+) -> Position {
+    Document::Text(raw_file_contents).to_position(index, encoding)
+}
+
+/// Re-expresses a position counted in `from` as one counted in `to`
+/// Positions are computed in UTF-16 internally and converted at the LSP boundary
+pub fn convert(
+    raw_file_contents: &str,
+    position: Position,
+    from: PositionEncoding,
+    to: PositionEncoding,
+) -> Position {
+    if from == to {
+        return position;
+    }
+    match to_index_in(raw_file_contents, position.line, position.character, from) {
+        Some(index) => to_position_in(raw_file_contents, index, to),
+        // past the last line, nothing to count in either encoding
+        None => position,
+    }
+}
+
+/// Line and column as users read them in an editor, both counted from 1
+/// LSP positions count from 0, convert only where output leaves the protocol
+pub fn to_one_based(position: Position) -> (u32, u32) {
+    (position.line + 1, position.character + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multibyte_offsets_differ_by_encoding() {
+        let contents = "{\n  \"name\": \"caf\u{e9} \u{1f680}\", \"port\": 1\n}";
+        let index = contents.find("\"port\"").unwrap();
 
-    let line_number = raw_file_contents[..safe_index]
-        .chars()
-        .filter(|x| *x == '\n')
-        .count() as u32;
+        let utf8 = to_position_in(contents, index, PositionEncoding::Utf8);
+        let utf16 = to_position_in(contents, index, PositionEncoding::Utf16);
 
-    trace!(
-        index = safe_index,
-        line_number = line_number,
-        "Calculated line number from index"
-    );
+        // é is 2 bytes but 1 code unit, the rocket 4 bytes but 2 code units
+        assert_eq!(utf8, Position::new(1, 24));
+        assert_eq!(utf16, Position::new(1, 21));
+        assert_eq!(
+            to_index_in(contents, 1, 24, PositionEncoding::Utf8),
+            Some(index)
+        );
+        assert_eq!(
+            convert(
+                contents,
+                utf16,
+                PositionEncoding::Utf16,
+                PositionEncoding::Utf8
+            ),
+            utf8
+        );
+    }
 
-    line_number
+    #[test]
+    fn test_negotiation_follows_client_preference() {
+        let offered = [PositionEncodingKind::UTF32, PositionEncodingKind::UTF8];
+        assert_eq!(
+            PositionEncoding::negotiate(Some(&offered)),
+            PositionEncoding::Utf8
+        );
+        assert_eq!(PositionEncoding::negotiate(None), PositionEncoding::Utf16);
+    }
 }
@@ -0,0 +1,90 @@
+use tracing::{debug, instrument, trace};
+
+/// A fenced code block of a Markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeFence {
+    /// Markdown line of the first line inside the fence
+    pub start_line: u32,
+    pub contents: String,
+}
+
+/// Extracts every ```json code fence from `markdown`
+/// An unclosed fence runs to the end of the document, like CommonMark
+#[instrument(skip(markdown), fields(markdown_len = markdown.len()))]
+pub fn json_fences(markdown: &str) -> Vec<CodeFence> {
+    let mut fences = Vec::new();
+    // json fence currently open, with its collected lines
+    let mut open: Option<(u32, Vec<&str>)> = None;
+    // a non-json fence is open, its contents are skipped
+    let mut in_other_fence = false;
+
+    for (line_idx, line) in markdown.lines().enumerate() {
+        let Some(info) = fence_marker(line) else {
+            if let Some((_, lines)) = open.as_mut() {
+                lines.push(line);
+            }
+            continue;
+        };
+
+        if let Some((start_line, lines)) = open.take() {
+            trace!(start_line, line_count = lines.len(), "Closed json fence");
+            fences.push(CodeFence {
+                start_line,
+                contents: lines.join("\n"),
+            });
+        } else if in_other_fence {
+            in_other_fence = false;
+        } else if info.split_whitespace().next() == Some("json") {
+            open = Some((line_idx as u32 + 1, Vec::new()));
+        } else {
+            in_other_fence = true;
+        }
+    }
+
+    if let Some((start_line, lines)) = open {
+        debug!(start_line, "Unclosed json fence");
+        fences.push(CodeFence {
+            start_line,
+            contents: lines.join("\n"),
+        });
+    }
+
+    fences
+}
+
+/// Info string of a ``` fence line, None for any other line
+fn fence_marker(line: &str) -> Option<&str> {
+    // up to three spaces of indentation are allowed before the fence
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    trimmed.strip_prefix("```").map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_only_json_fences() {
+        let markdown = "# Config\n\n```json\n{ \"a\": 1 }\n```\n\n```yaml\na: 1\n```\n\n  ```json title=\"x\"\n[]\n";
+
+        let fences = json_fences(markdown);
+
+        assert_eq!(
+            fences,
+            vec![
+                CodeFence {
+                    start_line: 3,
+                    contents: "{ \"a\": 1 }".to_string(),
+                },
+                CodeFence {
+                    start_line: 11,
+                    contents: "[]".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::sync::OnceLock;
 
 use regex::Regex;
@@ -5,6 +6,8 @@ use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
 use tracing::{debug, error, instrument, trace, warn};
 
 use crate::error::SchemaValidationError;
+use crate::line_number;
+use crate::value_span;
 
 /// Returns a Schema identifier if one can be found, trying to use the standard for the particular language, otherwise falling back to a regex solution
 pub fn extract_schema_reference(content: &serde_json::Value) -> Option<String> {
@@ -13,8 +16,142 @@ pub fn extract_schema_reference(content: &serde_json::Value) -> Option<String> {
         return Some(schema);
     }
 
-    // Fall back to JSON $schema field
-    Some(content.get("$schema")?.to_string())
+    // Fall back to JSON $schema field, only string values can identify a schema
+    match content.get("$schema")? {
+        serde_json::Value::String(schema) => Some(schema.clone()),
+        other => {
+            debug!(value = %other, "Ignoring non-string $schema field");
+            None
+        }
+    }
+}
+
+/// Schema identifier the document text declares, like `extract_schema_reference`
+/// Reads the `$schema` member without parsing the whole document, so a syntax error
+/// elsewhere keeps the declaration
+pub fn declared_schema(contents: &str) -> Option<String> {
+    if let Some(schema) = check_shebang_schema(contents) {
+        return Some(schema);
+    }
+
+    let (start, end) = value_span::locate("/$schema", contents)?;
+    serde_json::from_str::<String>(&contents[start..end]).ok()
+}
+
+/// Where the document declares its schema: the `#$schema` shebang on the first line,
+/// or else the key of a string `$schema` field
+/// None when the document declares no schema the way `extract_schema_reference` accepts
+#[instrument(skip_all)]
+pub fn schema_declaration_range(contents: &str) -> Option<Range> {
+    if check_shebang_schema(contents).is_some() {
+        let first_line = contents.lines().next()?;
+        let declaration = first_line.trim_end();
+        return Some(Range {
+            start: Position::new(0, 0),
+            end: Position::new(0, declaration.encode_utf16().count() as u32),
+        });
+    }
+
+    let json: serde_json::Value = serde_json::from_str(contents).ok()?;
+    extract_schema_reference(&json)?;
+    crate::json_pointer::key_range("/$schema", contents)
+}
+
+/// Warning placed on the opening of the document when it does not declare a schema
+pub fn missing_schema_diagnostic() -> Diagnostic {
+    Diagnostic {
+        range: Range {
+            start: Position {
+                line: 0,
+                character: 0,
+            },
+            end: Position {
+                line: 0,
+                character: 1,
+            },
+        },
+        message: "Document does not declare a $schema".to_string(),
+        severity: Some(DiagnosticSeverity::WARNING),
+        ..Default::default()
+    }
+}
+
+/// Error for a document with no content at all, in place of serde_json's EOF error
+/// Spans the first character, the document has nothing else to point at
+pub fn empty_document_diagnostic() -> Diagnostic {
+    Diagnostic {
+        range: Range {
+            start: Position {
+                line: 0,
+                character: 0,
+            },
+            end: Position {
+                line: 0,
+                character: 1,
+            },
+        },
+        message: SchemaValidationError::EmptyFileContents.to_string(),
+        severity: Some(DiagnosticSeverity::ERROR),
+        ..Default::default()
+    }
+}
+
+/// Warning in place of validation for a document over `Config::max_document_bytes`
+/// Spans the first character like the empty document error, nothing was parsed to point at
+pub fn oversized_document_diagnostic(limit: usize) -> Diagnostic {
+    Diagnostic {
+        range: Range {
+            start: Position {
+                line: 0,
+                character: 0,
+            },
+            end: Position {
+                line: 0,
+                character: 1,
+            },
+        },
+        message: format!(
+            "{}, not validated",
+            SchemaValidationError::DocumentTooLarge { limit }
+        ),
+        severity: Some(DiagnosticSeverity::WARNING),
+        ..Default::default()
+    }
+}
+
+/// Error on the key of an embedded schema (see `Config::embedded_schema_key`) that
+/// does not compile, the document can't be validated against it
+pub fn invalid_embedded_schema_diagnostic(
+    key: &str,
+    error: &SchemaValidationError,
+    file_contents: &str,
+) -> Diagnostic {
+    let pointer = format!("/{}", crate::cursor::escape(key));
+    Diagnostic {
+        range: crate::json_pointer::key_range(&pointer, file_contents).unwrap_or_default(),
+        message: format!("Embedded schema \"{}\" is invalid: {}", key, error),
+        severity: Some(DiagnosticSeverity::ERROR),
+        ..Default::default()
+    }
+}
+
+/// Error spanning the root value when the document is not an object
+pub fn non_object_root_diagnostic(json: &serde_json::Value, file_contents: &str) -> Diagnostic {
+    let kind = match json {
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Null => "null",
+        serde_json::Value::Object(_) => "an object",
+    };
+
+    Diagnostic {
+        range: crate::json_pointer::value_range("", file_contents).unwrap_or_default(),
+        message: format!("Document root must be an object, found {}", kind),
+        severity: Some(DiagnosticSeverity::ERROR),
+        ..Default::default()
+    }
 }
 
 /// Check for shebang-style: #$schema IDENTIFIER
@@ -36,10 +173,91 @@ fn check_shebang_schema(content: &str) -> Option<String> {
         .map(|m| m.as_str().to_string())
 }
 
+/// Dialect of a document, from the client's language id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceLanguage {
+    #[default]
+    Json,
+    /// JSON with `//` and `/* */` comments
+    Jsonc,
+}
+
+impl SourceLanguage {
+    /// Unknown language ids are treated as plain JSON
+    pub fn from_language_id(language_id: &str) -> Self {
+        match language_id {
+            "jsonc" => SourceLanguage::Jsonc,
+            _ => SourceLanguage::Json,
+        }
+    }
+
+    /// Text the JSON parser can read, with positions matching the original document
+    pub fn normalize<'a>(&self, file_contents: &'a str) -> Cow<'a, str> {
+        match self {
+            SourceLanguage::Json => Cow::Borrowed(file_contents),
+            SourceLanguage::Jsonc => Cow::Owned(strip_comments(file_contents)),
+        }
+    }
+}
+
+/// Blanks out comments, keeping line breaks and UTF-16 widths so positions don't move
+fn strip_comments(file_contents: &str) -> String {
+    let mut stripped = String::with_capacity(file_contents.len());
+    let mut chars = file_contents.chars().peekable();
+    let mut in_string = false;
+
+    let blank = |ch: char, out: &mut String| match ch {
+        '\n' | '\r' => out.push(ch),
+        _ => (0..ch.len_utf16()).for_each(|_| out.push(' ')),
+    };
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => in_string = !in_string,
+            '\\' if in_string => {
+                stripped.push(ch);
+                if let Some(escaped) = chars.next() {
+                    stripped.push(escaped);
+                }
+                continue;
+            }
+            '/' if !in_string && chars.peek() == Some(&'/') => {
+                blank(ch, &mut stripped);
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    blank(next, &mut stripped);
+                    chars.next();
+                }
+                continue;
+            }
+            '/' if !in_string && chars.peek() == Some(&'*') => {
+                blank(ch, &mut stripped);
+                blank('*', &mut stripped);
+                chars.next();
+                let mut previous = '\0';
+                for next in chars.by_ref() {
+                    blank(next, &mut stripped);
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        stripped.push(ch);
+    }
+
+    stripped
+}
+
 /// Internal enum to represent parsed content state
 pub enum ParsedContent {
     Valid(serde_json::Value),
-    ParseError(Diagnostic),
+    ParseError(Box<Diagnostic>),
 }
 
 impl ParsedContent {
@@ -48,6 +266,13 @@ impl ParsedContent {
     pub fn new(file_contents: &str) -> Result<Self, SchemaValidationError> {
         trace!("Attempting to parse file contents as JSON");
 
+        if file_contents.trim().is_empty() {
+            warn!("Document is empty");
+            return Ok(ParsedContent::ParseError(Box::new(
+                empty_document_diagnostic(),
+            )));
+        }
+
         match serde_json::from_str(file_contents) {
             Ok(json) => {
                 debug!("Successfully parsed JSON content");
@@ -55,39 +280,197 @@ impl ParsedContent {
             }
             Err(e) => {
                 error!(error = %e, "JSON parsing failed");
-                Ok(ParsedContent::ParseError(
-                    ParseErrorDiagnostic::from(e).into(),
-                ))
+                Ok(ParsedContent::ParseError(Box::new(
+                    ParseErrorDiagnostic::new(e, file_contents).into(),
+                )))
             }
         }
     }
 }
 
+/// Reports the syntax errors of `file_contents` one after another instead of only the first
+/// Each cycle applies the obvious fix for the error (inserting a missing comma, blanking a
+/// trailing comma) and reparses, for at most `max_iterations` cycles; the error that can't
+/// be fixed, or is left when the bound is hit, is the last one reported
+///
+/// Fixes never add or remove line breaks, and every diagnostic is mapped back past the
+/// inserted commas, so positions and messages refer to the original text
+#[instrument(skip(file_contents), fields(content_len = file_contents.len()))]
+pub fn recover_parse_errors(file_contents: &str, max_iterations: usize) -> Vec<Diagnostic> {
+    if file_contents.trim().is_empty() {
+        return vec![empty_document_diagnostic()];
+    }
+
+    let mut text = file_contents.to_owned();
+    // offsets in `text` of the commas inserted so far, ascending
+    let mut inserted: Vec<usize> = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for iteration in 0..=max_iterations {
+        let Err(error) = serde_json::from_str::<serde_json::Value>(&text) else {
+            break;
+        };
+        let message = error.to_string();
+        let diagnostic = ParseErrorDiagnostic::new(error, &text);
+        let offset = diagnostic.offset();
+        diagnostics.push(diagnostic.relocated(file_contents, &inserted).into());
+
+        let fixed = if iteration < max_iterations {
+            fix(&mut text, &message, offset, &mut inserted)
+        } else {
+            debug!(max_iterations, "Parse recovery iteration limit reached");
+            false
+        };
+        if !fixed {
+            break;
+        }
+        trace!(iteration, "Applied parse recovery fix, reparsing");
+    }
+
+    diagnostics
+}
+
+/// Applies the fix for the parse error `message` at `offset` to `text`, if obvious
+/// A missing comma is inserted before the next member, its offset recorded in `inserted`
+fn fix(text: &mut String, message: &str, offset: usize, inserted: &mut Vec<usize>) -> bool {
+    if message.starts_with("expected `,`") {
+        text.insert(offset, ',');
+        let at = inserted.partition_point(|&idx| idx < offset);
+        // commas further down moved along with the text
+        for idx in &mut inserted[at..] {
+            *idx += 1;
+        }
+        inserted.insert(at, offset);
+        return true;
+    }
+
+    if message.starts_with("trailing comma") {
+        let bytes = text.as_bytes();
+        let comma = bytes[..offset.min(bytes.len())]
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .filter(|&comma| bytes[comma] == b',');
+        if let Some(comma) = comma {
+            text.replace_range(comma..comma + 1, " ");
+            return true;
+        }
+    }
+
+    false
+}
+
 /// Wrapper for creating parse error diagnostics
 pub struct ParseErrorDiagnostic {
     line: u32,
-    column: u32,
+    offset: usize,
+    // UTF-16 characters the underline spans on `line`, the token the error points at
+    start_character: u32,
+    end_character: u32,
     message: String,
 }
 
-impl From<serde_json::Error> for ParseErrorDiagnostic {
-    #[instrument(skip(error), fields(line = error.line(), column = error.column()))]
-    fn from(error: serde_json::Error) -> Self {
-        let (line, column) = (error.line() as u32 - 1, error.column() as u32);
+impl ParseErrorDiagnostic {
+    /// Builds the diagnostic for `error`, raised while parsing `file_contents`
+    #[instrument(skip(error, file_contents), fields(line = error.line(), column = error.column()))]
+    pub fn new(error: serde_json::Error, file_contents: &str) -> Self {
+        let (line, column) = (error.line().saturating_sub(1) as u32, error.column() as u32);
+
+        // serde_json columns are 1-based byte counts from the start of the line
+        let line_start = match line {
+            0 => 0,
+            line => file_contents
+                .match_indices('\n')
+                .nth(line as usize - 1)
+                .map_or(file_contents.len(), |(idx, _)| idx + 1),
+        };
+        let offset = (line_start + (column as usize).saturating_sub(1)).min(file_contents.len());
+        Self::at(line, offset, error.to_string(), file_contents)
+    }
+
+    /// Diagnostic with `message` at byte `offset` of `line`
+    fn at(line: u32, offset: usize, message: String, file_contents: &str) -> Self {
+        let start_character = line_number::to_position(file_contents, offset).character;
+        let end_character =
+            line_number::to_position(file_contents, token_end(file_contents, offset)).character;
 
         trace!(
             line = line,
-            column = column,
-            error = %error,
+            offset = offset,
+            start_character = start_character,
+            end_character = end_character,
+            error = %message,
             "Creating parse error diagnostic"
         );
 
         Self {
             line,
-            column,
-            message: error.to_string(),
+            offset,
+            start_character,
+            end_character,
+            message,
+        }
+    }
+
+    /// The diagnostic moved from recovered text back into `original`, before the commas
+    /// inserted at the ascending text offsets `inserted`; its message location follows
+    fn relocated(self, original: &str, inserted: &[usize]) -> Self {
+        if inserted.is_empty() {
+            return self;
+        }
+
+        let offset = self.offset - inserted.partition_point(|&idx| idx < self.offset);
+        let line = line_number::to_position(original, offset).line;
+        let line_start = original[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+        let message = match self.message.rsplit_once(" at line ") {
+            Some((message, _)) => format!(
+                "{} at line {} column {}",
+                message,
+                line + 1,
+                offset - line_start + 1
+            ),
+            None => self.message,
+        };
+        Self::at(line, offset, message, original)
+    }
+
+    /// Absolute byte offset of the parse error in the source
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// End of the token starting at `offset`: scans forward to the next `,`, `}`, `]` or line
+/// break outside a string literal, trailing whitespace excluded
+/// A delimiter at `offset` is the token itself
+fn token_end(file_contents: &str, offset: usize) -> usize {
+    let bytes = file_contents.as_bytes();
+    let line_end = bytes[offset..]
+        .iter()
+        .position(|b| matches!(b, b'\n' | b'\r'))
+        .map_or(bytes.len(), |len| offset + len);
+
+    let mut stop = offset;
+    while stop < line_end {
+        match bytes[stop] {
+            b',' | b'}' | b']' => break,
+            // delimiters inside a string are text, an unterminated one ends with its line
+            b'"' => {
+                stop = value_span::string_end(&file_contents[..line_end], stop).unwrap_or(line_end)
+            }
+            _ => stop += 1,
         }
     }
+
+    if stop == offset {
+        return match bytes.get(offset) {
+            Some(b',' | b'}' | b']') => offset + 1,
+            _ => offset,
+        };
+    }
+    bytes[offset..stop]
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(offset, |last| offset + last + 1)
 }
 
 impl From<ParseErrorDiagnostic> for Diagnostic {
@@ -97,18 +480,200 @@ impl From<ParseErrorDiagnostic> for Diagnostic {
                 // can fail if usize > size of u32
                 start: Position {
                     line: diag.line,
-                    character: 0,
+                    character: diag.start_character,
                 },
+                // at least one character wide, editors skip empty ranges
                 end: Position {
                     line: diag.line,
-                    character: diag.column.saturating_sub(1),
+                    character: diag.end_character.max(diag.start_character + 1),
                 },
             },
             // Note could use a DiagnosticRelatedInformation struct here instead.. as it
             // points to the error in source code where error occurs.. Come back here
             message: diag.message,
             severity: Some(DiagnosticSeverity::ERROR),
+            data: Some(serde_json::json!({ "offset": diag.offset })),
             ..Default::default()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_schema_reference_is_extracted() {
+        let content = serde_json::json!({ "$schema": "service.schema" });
+
+        assert_eq!(
+            extract_schema_reference(&content).as_deref(),
+            Some("service.schema")
+        );
+    }
+
+    #[test]
+    fn test_numeric_schema_reference_is_ignored() {
+        let content = serde_json::json!({ "$schema": 7 });
+
+        assert_eq!(extract_schema_reference(&content), None);
+    }
+
+    #[test]
+    fn test_object_schema_reference_is_ignored() {
+        let content = serde_json::json!({ "$schema": { "id": "service.schema" } });
+
+        assert_eq!(extract_schema_reference(&content), None);
+    }
+
+    #[test]
+    fn test_parse_error_offset_points_at_offending_character() {
+        let contents = "{\n  \"service\": \"api\"\n  \"version\": \"1.2.3\"\n}";
+        let error = serde_json::from_str::<serde_json::Value>(contents).unwrap_err();
+
+        let diagnostic = ParseErrorDiagnostic::new(error, contents);
+
+        assert_eq!(&contents[diagnostic.offset()..][..9], "\"version\"");
+    }
+
+    #[test]
+    fn test_parse_recovery_reports_sequential_errors() {
+        let contents = "{\n  \"a\": 1\n  \"b\": 2,\n  \"c\": [1, 2,],\n  \"d\": 4\n  \"e\": 5\n}";
+
+        let lines = |diagnostics: Vec<Diagnostic>| -> Vec<u32> {
+            diagnostics.iter().map(|d| d.range.start.line).collect()
+        };
+
+        assert_eq!(lines(recover_parse_errors(contents, 10)), vec![2, 3, 5]);
+        // without recovery only the first error is reported, like ParsedContent
+        assert_eq!(lines(recover_parse_errors(contents, 0)), vec![2]);
+    }
+
+    #[test]
+    fn test_parse_recovery_keeps_lines_of_unindented_members() {
+        let contents = "{\n\"a\": 1\n\"b\": 2\n\"c\": 3\n}";
+
+        let diagnostics = recover_parse_errors(contents, 10);
+
+        let located: Vec<(u32, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            located,
+            vec![
+                (2, "expected `,` or `}` at line 3 column 1"),
+                (3, "expected `,` or `}` at line 4 column 1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_recovery_stops_at_iteration_limit() {
+        let members: Vec<String> = (0..20)
+            .map(|idx| format!("\"k{}\": {}", idx, idx))
+            .collect();
+        let contents = format!("{{\n{}\n}}", members.join("\n"));
+
+        // every missing comma is fixable, the bound is what ends the loop
+        assert_eq!(recover_parse_errors(&contents, 3).len(), 4);
+        assert_eq!(recover_parse_errors(&contents, 10).len(), 11);
+
+        // an error without an obvious fix ends recovery right away
+        assert_eq!(recover_parse_errors("{ \"a\": }", 10).len(), 1);
+    }
+
+    #[test]
+    fn test_whitespace_only_document_is_reported_empty() {
+        for contents in ["", "   \n"] {
+            let ParsedContent::ParseError(diagnostic) = ParsedContent::new(contents).unwrap()
+            else {
+                panic!("{:?} parsed", contents);
+            };
+
+            assert_eq!(diagnostic.message, "Document is empty");
+            assert_eq!(diagnostic.range.start, Position::new(0, 0));
+            assert_eq!(
+                recover_parse_errors(contents, 10),
+                vec![*diagnostic.clone()]
+            );
+            assert_eq!(
+                crate::validate_liberally(&serde_json::json!({}), contents).unwrap(),
+                vec![*diagnostic]
+            );
+        }
+    }
+
+    #[test]
+    fn test_shebang_schema_declaration_spans_first_line() {
+        let contents = "#$schema service.schema\n{ \"service\": \"api\" }";
+
+        assert_eq!(
+            schema_declaration_range(contents),
+            Some(Range::new(Position::new(0, 0), Position::new(0, 23)))
+        );
+        assert_eq!(schema_declaration_range(r#"{ "$schema": 7 }"#), None);
+    }
+
+    #[test]
+    fn test_missing_comma_underlines_offending_token() {
+        let contents = "{\n  \"service\": \"api\"\n  \"version\": \"1.2.3\", \"port\": 80\n}";
+        let error = serde_json::from_str::<serde_json::Value>(contents).unwrap_err();
+
+        let diagnostic: Diagnostic = ParseErrorDiagnostic::new(error, contents).into();
+
+        let line = contents
+            .lines()
+            .nth(diagnostic.range.end.line as usize)
+            .unwrap();
+        assert_eq!(
+            &line[..diagnostic.range.end.character as usize],
+            "  \"version\": \"1.2.3\""
+        );
+    }
+
+    #[test]
+    fn test_error_range_starts_at_token_and_skips_string_delimiters() {
+        let contents = "{\n  \"tags\": [\"a\"]\n  \"motd\": \"a,b}\", \"port\": 80\n}";
+        let error = serde_json::from_str::<serde_json::Value>(contents).unwrap_err();
+
+        let diagnostic: Diagnostic = ParseErrorDiagnostic::new(error, contents).into();
+
+        let line = contents.lines().nth(2).unwrap();
+        let range = diagnostic.range;
+        assert_eq!(range.start.line, 2);
+        assert_eq!(
+            &line[range.start.character as usize..range.end.character as usize],
+            "\"motd\": \"a,b}\""
+        );
+    }
+
+    #[test]
+    fn test_column_zero_error_range_is_not_empty() {
+        // EOF right after a line break is reported at column 0
+        let contents = "{\n  \"service\": \"api\",\n";
+        let error = serde_json::from_str::<serde_json::Value>(contents).unwrap_err();
+        assert_eq!(error.column(), 0);
+
+        let diagnostic: Diagnostic = ParseErrorDiagnostic::new(error, contents).into();
+
+        assert_eq!(diagnostic.range.start, Position::new(2, 0));
+        assert_eq!(diagnostic.range.end, Position::new(2, 1));
+    }
+
+    #[test]
+    fn test_jsonc_comments_are_blanked_in_place() {
+        let contents = "{\n  // service name\n  \"url\": \"http://x\", /* inline */ \"port\": 1\n}";
+
+        let normalized = SourceLanguage::from_language_id("jsonc").normalize(contents);
+
+        assert!(serde_json::from_str::<serde_json::Value>(&normalized).is_ok());
+        assert_eq!(normalized.len(), contents.len());
+        assert_eq!(normalized.find("\"port\""), contents.find("\"port\""));
+        assert!(normalized.contains("\"http://x\""));
+        assert!(matches!(
+            SourceLanguage::from_language_id("json").normalize(contents),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,24 @@
+//! Commonly used types and functions, re-exported for a stable import path
+//!
+//! ```
+//! use pur::prelude::*;
+//!
+//! let schema = serde_json::json!({ "type": "object", "required": ["service"] });
+//!
+//! let diagnostics: ValidationResult<_> = validate_liberally(&schema, "{}");
+//! assert_eq!(diagnostics.unwrap().len(), 1);
+//!
+//! match ParsedContent::new("{").unwrap() {
+//!     ParsedContent::Valid(_) => unreachable!(),
+//!     ParsedContent::ParseError(diagnostic) => assert!(!diagnostic.message.is_empty()),
+//! }
+//! ```
+
+pub use crate::config::Config;
+pub use crate::error::{SchemaValidationError, ValidationResult};
+pub use crate::parsing::ParsedContent;
+pub use crate::validation::{
+    CompiledSchema, SchemaValidator, ValidationFinding, ValidationKind, detect_draft,
+    iter_raw_errors,
+};
+pub use crate::{autofix, validate_liberally, validate_subtree, validate_with_config};
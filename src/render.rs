@@ -0,0 +1,153 @@
+use tower_lsp::lsp_types::{Diagnostic, NumberOrString};
+
+use crate::{cli, line_number};
+
+/// Renders `diagnostic` rustc style: the message, then every source line its range
+/// covers with carets under the covered part
+///
+/// ```text
+/// error: "80" is not of type "integer"
+///  --> 3:13
+///   |
+/// 3 |     "port": "80"
+///   |             ^^^^
+/// ```
+pub fn render_diagnostic_with_context(diagnostic: &Diagnostic, contents: &str) -> String {
+    let range = diagnostic.range;
+    let lines: Vec<&str> = contents.split('\n').collect();
+    // gutter wide enough for the largest line number shown
+    let width = (range.end.line + 1).to_string().len();
+    let (line, column) = line_number::to_one_based(range.start);
+
+    let mut rendered = format!(
+        "{}: {}\n{:width$}--> {}:{}\n{:width$} |\n",
+        cli::severity_name(diagnostic.severity),
+        diagnostic.message,
+        "",
+        line,
+        column,
+        "",
+    );
+
+    for line in range.start.line..=range.end.line {
+        let Some(text) = lines.get(line as usize) else {
+            break;
+        };
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let line_chars = text.chars().count();
+
+        let start = if line == range.start.line {
+            char_column(text, range.start.character)
+        } else {
+            0
+        };
+        let end = if line == range.end.line {
+            char_column(text, range.end.character)
+        } else {
+            line_chars
+        };
+        // an empty range still points somewhere
+        let carets = end.saturating_sub(start).max(1);
+
+        rendered.push_str(&format!("{:>width$} | {}\n", line + 1, text));
+        rendered.push_str(&format!(
+            "{:width$} | {}{}\n",
+            "",
+            " ".repeat(start),
+            "^".repeat(carets)
+        ));
+    }
+
+    rendered
+}
+
+/// Character column of the UTF-16 `character` offset within `line`, for caret alignment
+fn char_column(line: &str, character: u32) -> usize {
+    let index = line_number::to_index(line, 0, character).unwrap_or(line.len());
+    line[..index].chars().count()
+}
+
+/// Trimmed, deterministic JSON form of `diagnostics` for snapshot style assertions
+/// Keeps the start position, severity, code and message, sorted by position then message
+pub fn diagnostics_to_snapshot(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let mut ordered: Vec<&Diagnostic> = diagnostics.iter().collect();
+    ordered.sort_by(|a, b| (a.range.start, &a.message).cmp(&(b.range.start, &b.message)));
+
+    ordered
+        .into_iter()
+        .map(|diagnostic| {
+            let code = match &diagnostic.code {
+                Some(NumberOrString::Number(code)) => serde_json::Value::from(*code),
+                Some(NumberOrString::String(code)) => serde_json::Value::from(code.as_str()),
+                None => serde_json::Value::Null,
+            };
+            serde_json::json!({
+                "line": diagnostic.range.start.line,
+                "character": diagnostic.range.start.character,
+                "severity": cli::severity_name(diagnostic.severity),
+                "code": code,
+                "message": diagnostic.message,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::{DiagnosticSeverity, Position, Range};
+
+    fn diagnostic(start: (u32, u32), end: (u32, u32)) -> Diagnostic {
+        Diagnostic {
+            range: Range {
+                start: Position {
+                    line: start.0,
+                    character: start.1,
+                },
+                end: Position {
+                    line: end.0,
+                    character: end.1,
+                },
+            },
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: "bad value".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_single_line_range_is_underlined() {
+        let contents = "{\n  \"port\": \"80\"\n}";
+
+        let rendered = render_diagnostic_with_context(&diagnostic((1, 10), (1, 14)), contents);
+
+        assert_eq!(
+            rendered,
+            "error: bad value\n --> 2:11\n  |\n2 |   \"port\": \"80\"\n  |           ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_multi_line_range_underlines_every_line() {
+        let contents = "{\n  \"runtime\": {\n    \"image\": 1\n  }\n}";
+
+        let rendered = render_diagnostic_with_context(&diagnostic((1, 13), (3, 3)), contents);
+
+        assert_eq!(
+            rendered,
+            [
+                "error: bad value",
+                " --> 2:14",
+                "  |",
+                "2 |   \"runtime\": {",
+                "  |              ^",
+                "3 |     \"image\": 1",
+                "  | ^^^^^^^^^^^^^^",
+                "4 |   }",
+                "  | ^^^",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
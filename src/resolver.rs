@@ -0,0 +1,680 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Url};
+use tracing::{debug, instrument, warn};
+
+use crate::error::{SchemaValidationError, ValidationResult};
+use crate::introspection;
+
+// COME BACK HERE LATER FOR EMBEDDING MORE JSON SCHEMAS
+const SERVICE_SCHEMA: &str = include_str!("../schemas/service.schema.json");
+
+/// Source of Json Schemas by identifier
+/// Implement this to serve schemas from a database, registry, etc when embedding the server
+#[tower_lsp::async_trait]
+pub trait SchemaResolver: Send + Sync {
+    async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>>;
+
+    /// Notices about how `id` was last resolved, published alongside its diagnostics
+    async fn notices(&self, _id: &str) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+}
+
+/// Shared resolvers, eg the backend's, can be wrapped by `CachedSchemaResolver`
+#[tower_lsp::async_trait]
+impl<R: SchemaResolver + ?Sized> SchemaResolver for Arc<R> {
+    async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+        (**self).resolve(id).await
+    }
+
+    async fn notices(&self, id: &str) -> Vec<Diagnostic> {
+        (**self).notices(id).await
+    }
+}
+
+/// Wraps a resolver fetching remote schemas, keeping the last good copy of every schema
+/// in `cache_dir`. When fetching fails (eg no network in CI) the cached copy is served
+/// and an INFORMATION notice reports the schema may be stale
+pub struct CachedSchemaResolver<R> {
+    inner: R,
+    cache_dir: PathBuf,
+    // ids currently served from the cache
+    stale: std::sync::Mutex<HashSet<String>>,
+}
+
+impl<R: SchemaResolver> CachedSchemaResolver<R> {
+    pub fn new(inner: R, cache_dir: PathBuf) -> Self {
+        Self {
+            inner,
+            cache_dir,
+            stale: Default::default(),
+        }
+    }
+
+    /// Cache file of `id`: the id flattened into a readable name, plus a hash of the id
+    /// itself so ids flattening alike (eg `a/b` and `a_b`) get files of their own
+    fn cache_path(&self, id: &str) -> PathBuf {
+        let flattened: String = id
+            .chars()
+            .map(|ch| {
+                if ch.is_ascii_alphanumeric() || ch == '.' {
+                    ch
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        // only ASCII is left, any byte index is a char boundary
+        let readable = &flattened[flattened.len().saturating_sub(MAX_CACHE_NAME_LEN)..];
+        self.cache_dir
+            .join(format!("{:016x}-{}", fnv1a(id.as_bytes()), readable))
+    }
+}
+
+#[tower_lsp::async_trait]
+impl<R: SchemaResolver> SchemaResolver for CachedSchemaResolver<R> {
+    #[instrument(skip(self))]
+    async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+        let path = self.cache_path(id);
+
+        match self.inner.resolve(id).await {
+            Ok(schema) => {
+                self.stale.lock().expect("stale set poisoned").remove(id);
+                // a failing cache write only costs the offline fallback, not this resolve
+                if let Err(e) = write_cache(&path, &schema).await {
+                    warn!(path = %path.display(), error = %e, "Failed to cache schema");
+                }
+                Ok(schema)
+            }
+            Err(e) => {
+                let Ok(raw) = tokio::fs::read_to_string(&path).await else {
+                    return Err(e);
+                };
+                warn!(error = %e, "Schema fetch failed, using cached copy");
+                self.stale
+                    .lock()
+                    .expect("stale set poisoned")
+                    .insert(id.to_string());
+                Ok(Arc::new(serde_json::from_str(&raw)?))
+            }
+        }
+    }
+
+    async fn notices(&self, id: &str) -> Vec<Diagnostic> {
+        let mut notices = self.inner.notices(id).await;
+        if self.stale.lock().expect("stale set poisoned").contains(id) {
+            notices.push(Diagnostic {
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                message: format!(
+                    "Schema {} could not be fetched, validated against a stale cached copy",
+                    id
+                ),
+                ..Default::default()
+            });
+        }
+        notices
+    }
+}
+
+/// Characters of an id kept in its cache file name, its end names the schema best
+const MAX_CACHE_NAME_LEN: usize = 64;
+
+/// 64 bit FNV-1a, stable across builds unlike `std`'s hasher, so cache file names are too
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+async fn write_cache(path: &std::path::Path, schema: &Value) -> ValidationResult<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(path, serde_json::to_vec(schema)?).await?;
+    Ok(())
+}
+
+/// Scopes another resolver to a directory: a relative id is first resolved as a path
+/// inside `dir`, ids the directory does not have fall back to the resolver as they are
+pub struct ScopedSchemaResolver {
+    inner: Arc<dyn SchemaResolver>,
+    dir: PathBuf,
+}
+
+impl ScopedSchemaResolver {
+    pub fn new(inner: Arc<dyn SchemaResolver>, dir: PathBuf) -> Self {
+        Self { inner, dir }
+    }
+
+    /// `id` inside the directory, None for URLs and absolute paths
+    fn scoped_id(&self, id: &str) -> Option<String> {
+        (!id.contains("://") && std::path::Path::new(id).is_relative())
+            .then(|| self.dir.join(id).display().to_string())
+    }
+}
+
+#[tower_lsp::async_trait]
+impl SchemaResolver for ScopedSchemaResolver {
+    #[instrument(skip(self), fields(dir = %self.dir.display()))]
+    async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+        if let Some(scoped) = self.scoped_id(id) {
+            match self.inner.resolve(&scoped).await {
+                Err(SchemaValidationError::SchemaNotFound(_)) => {
+                    debug!(scoped, "Not in the schema directory, resolving as is");
+                }
+                resolved => return resolved,
+            }
+        }
+        self.inner.resolve(id).await
+    }
+
+    async fn notices(&self, id: &str) -> Vec<Diagnostic> {
+        let mut notices = self.inner.notices(id).await;
+        if let Some(scoped) = self.scoped_id(id) {
+            notices.extend(self.inner.notices(&scoped).await);
+        }
+        notices
+    }
+}
+
+/// Base `jsonschema` resolves references against in schemas without an `$id`
+const DEFAULT_BASE_URI: &str = "json-schema:///";
+
+/// How long `resolve_references` waits for a single referenced schema
+pub const REF_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Loads every schema `json_schema` references through a `$ref` to another document,
+/// transitively. Results are keyed by the absolute URI each reference resolves to, which
+/// is where `jsonschema` looks them up. Every schema is resolved once, a visited set stops cycles
+///
+/// Each URI is resolved by the id `resolver_id` gives it, absolute URLs as they are
+pub async fn resolve_references(
+    resolver: &dyn SchemaResolver,
+    json_schema: &Value,
+) -> ValidationResult<HashMap<String, Arc<Value>>> {
+    resolve_references_within(resolver, json_schema, REF_FETCH_TIMEOUT).await
+}
+
+/// `resolve_references` giving every referenced schema `timeout` to resolve
+/// A reference failing or timing out is a compilation error naming that `$ref`
+#[instrument(skip(resolver, json_schema))]
+pub async fn resolve_references_within(
+    resolver: &dyn SchemaResolver,
+    json_schema: &Value,
+    timeout: Duration,
+) -> ValidationResult<HashMap<String, Arc<Value>>> {
+    let mut resources = HashMap::new();
+    let mut visited = HashSet::new();
+    // (base URI of the referencing schema, its references) still to load
+    let mut pending = vec![(
+        base_uri(json_schema, DEFAULT_BASE_URI),
+        external_refs(json_schema),
+    )];
+
+    while let Some((base, references)) = pending.pop() {
+        for reference in references {
+            let Ok(uri) = base.join(&reference) else {
+                debug!(reference, "Unresolvable $ref");
+                continue;
+            };
+            // meta-schemas ship with `jsonschema`
+            if uri.host_str() == Some("json-schema.org") || !visited.insert(uri.to_string()) {
+                continue;
+            }
+
+            let id = resolver_id(&uri);
+            debug!(reference, uri = %uri, id, "Loading referenced schema");
+            let cause = match tokio::time::timeout(timeout, resolver.resolve(&id)).await {
+                Ok(Ok(schema)) => {
+                    pending.push((base_uri(&schema, uri.as_str()), external_refs(&schema)));
+                    resources.insert(uri.to_string(), schema);
+                    continue;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => format!("timed out after {:?}", timeout),
+            };
+            warn!(reference, uri = %uri, cause = %cause, "Failed to load referenced schema");
+            return Err(SchemaValidationError::ValidatorCompilationError(format!(
+                "unresolved $ref {} ({}): {}",
+                reference, uri, cause
+            )));
+        }
+    }
+
+    Ok(resources)
+}
+
+/// Canonical form of a schema identifier, so equivalent spellings share one cache entry
+/// URLs lose their fragment (an empty `#` included) and get a lowercase scheme and host,
+/// other identifiers only lose a trailing `#`
+pub fn normalize_schema_id(id: &str) -> String {
+    match Url::parse(id) {
+        Ok(mut url) if url.has_host() => {
+            // parsing already lowercased the scheme and host
+            url.set_fragment(None);
+            url.to_string()
+        }
+        _ => id.split('#').next().unwrap_or_default().to_string(),
+    }
+}
+
+/// URI references inside `json_schema` resolve against, its `$id` or `fallback`
+fn base_uri(json_schema: &Value, fallback: &str) -> Url {
+    json_schema
+        .get("$id")
+        .and_then(Value::as_str)
+        .and_then(|id| Url::parse(id).ok())
+        .unwrap_or_else(|| Url::parse(fallback).expect("Valid fallback base URI"))
+}
+
+/// Id a `SchemaResolver` is asked for the schema at `uri`
+/// Paths under `DEFAULT_BASE_URI` become relative ids again (eg sub/b.json), file URLs
+/// become paths and any other URL is passed as it is
+fn resolver_id(uri: &Url) -> String {
+    match uri.scheme() {
+        "json-schema" => uri.path().trim_start_matches('/').to_string(),
+        "file" => match uri.to_file_path() {
+            Ok(path) => path.display().to_string(),
+            Err(()) => uri.to_string(),
+        },
+        _ => uri.to_string(),
+    }
+}
+
+/// `$ref` targets in other documents anywhere in `json_schema`, without their `#` fragment
+fn external_refs(json_schema: &Value) -> Vec<String> {
+    let mut references = Vec::new();
+
+    introspection::visit_schema(json_schema, |_, keyword, value| {
+        let Some(reference) = value.as_str().filter(|_| keyword == "$ref") else {
+            return;
+        };
+        let target = reference.split('#').next().unwrap_or_default();
+        if !target.is_empty() {
+            references.push(target.to_string());
+        }
+    });
+
+    references
+}
+
+/// Resolves schemas embedded in the binary first, then http(s) URLs over the network,
+/// then from the filesystem. Relative ids are looked up inside `schema_dir` when one is set
+#[derive(Debug, Default)]
+pub struct DefaultSchemaResolver {
+    schema_dir: Option<PathBuf>,
+}
+
+impl DefaultSchemaResolver {
+    pub fn new(schema_dir: Option<PathBuf>) -> Self {
+        Self { schema_dir }
+    }
+
+    fn embedded(id: &str) -> Option<&'static str> {
+        match id {
+            "service.schema" | "service.schema.json" => Some(SERVICE_SCHEMA),
+            _ => None,
+        }
+    }
+}
+
+#[tower_lsp::async_trait]
+impl SchemaResolver for DefaultSchemaResolver {
+    #[instrument(skip(self))]
+    async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+        if let Some(embedded) = Self::embedded(id) {
+            debug!("Resolved embedded schema");
+            return Ok(Arc::new(serde_json::from_str(embedded)?));
+        }
+        if id.starts_with("http://") || id.starts_with("https://") {
+            return fetch(id).await.map(Arc::new);
+        }
+
+        let path = match &self.schema_dir {
+            Some(dir) => dir.join(id),
+            None => PathBuf::from(id),
+        };
+
+        debug!(path = %path.display(), "Reading schema from file");
+        let raw = tokio::fs::read_to_string(&path).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                SchemaValidationError::SchemaNotFound(id.to_string())
+            } else {
+                e.into()
+            }
+        })?;
+
+        Ok(Arc::new(serde_json::from_str(&raw)?))
+    }
+}
+
+/// Downloads the schema at `url`, a missing document is `SchemaNotFound`
+async fn fetch(url: &str) -> ValidationResult<Value> {
+    debug!(url, "Fetching remote schema");
+    let fetch_error = |e: reqwest::Error| SchemaValidationError::SchemaFetchError(e.to_string());
+
+    let response = reqwest::get(url).await.map_err(fetch_error)?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(SchemaValidationError::SchemaNotFound(url.to_string()));
+    }
+    let body = response
+        .error_for_status()
+        .map_err(fetch_error)?
+        .bytes()
+        .await
+        .map_err(fetch_error)?;
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Resolves schemas by entry name from a zip bundle, for offline distribution
+/// Entries are read lazily on first request and cached afterwards
+#[cfg(feature = "zip")]
+pub struct BundleSchemaResolver {
+    source: BundleSource,
+    cache: std::sync::Mutex<std::collections::HashMap<String, Arc<Value>>>,
+}
+
+#[cfg(feature = "zip")]
+#[derive(Clone)]
+enum BundleSource {
+    Path(PathBuf),
+    Bytes(Arc<[u8]>),
+}
+
+#[cfg(feature = "zip")]
+impl BundleSchemaResolver {
+    /// Bundle read from an archive on disk
+    pub fn open(path: PathBuf) -> Self {
+        Self::from_source(BundleSource::Path(path))
+    }
+
+    /// Bundle held in memory, eg embedded with `include_bytes!`
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self::from_source(BundleSource::Bytes(bytes.into()))
+    }
+
+    fn from_source(source: BundleSource) -> Self {
+        Self {
+            source,
+            cache: Default::default(),
+        }
+    }
+
+    fn cached(&self, id: &str) -> Option<Arc<Value>> {
+        self.cache
+            .lock()
+            .expect("bundle cache poisoned")
+            .get(id)
+            .cloned()
+    }
+}
+
+#[cfg(feature = "zip")]
+impl BundleSource {
+    /// Unzips entry `id`, blocking on file IO for a bundle on disk
+    fn read_entry(&self, id: &str) -> ValidationResult<Value> {
+        match self {
+            BundleSource::Path(path) => Self::read_from(std::fs::File::open(path)?, id),
+            BundleSource::Bytes(bytes) => Self::read_from(std::io::Cursor::new(bytes), id),
+        }
+    }
+
+    fn read_from(reader: impl std::io::Read + std::io::Seek, id: &str) -> ValidationResult<Value> {
+        let mut archive = zip::ZipArchive::new(reader).map_err(std::io::Error::other)?;
+        let entry = archive.by_name(id).map_err(|e| match e {
+            zip::result::ZipError::FileNotFound => {
+                SchemaValidationError::SchemaNotFound(id.to_string())
+            }
+            other => std::io::Error::other(other).into(),
+        })?;
+
+        Ok(serde_json::from_reader(entry)?)
+    }
+}
+
+#[cfg(feature = "zip")]
+#[tower_lsp::async_trait]
+impl SchemaResolver for BundleSchemaResolver {
+    #[instrument(skip(self))]
+    async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+        if let Some(schema) = self.cached(id) {
+            return Ok(schema);
+        }
+
+        debug!("Reading schema from bundle");
+        // the archive is read off the async runtime, no lock is held meanwhile
+        let (source, entry) = (self.source.clone(), id.to_owned());
+        let schema = tokio::task::spawn_blocking(move || source.read_entry(&entry))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        // a concurrent resolve may have read it too, the first cached copy is shared
+        let mut cache = self.cache.lock().expect("bundle cache poisoned");
+        Ok(cache
+            .entry(id.to_string())
+            .or_insert_with(|| Arc::new(schema))
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_schema_ids_normalize_fragment_and_host() {
+        assert_eq!(
+            normalize_schema_id("HTTPS://Example.COM/s.json#"),
+            "https://example.com/s.json"
+        );
+        assert_eq!(normalize_schema_id("service.schema#"), "service.schema");
+        assert_eq!(normalize_schema_id("service.schema"), "service.schema");
+    }
+
+    #[tokio::test]
+    async fn test_default_resolver_serves_embedded_schema() {
+        let schema = DefaultSchemaResolver::default()
+            .resolve("service.schema")
+            .await
+            .expect("embedded schema");
+
+        assert_eq!(schema["$id"], "https://example.com/service.schema.json");
+    }
+
+    #[tokio::test]
+    async fn test_default_resolver_reports_missing_schema() {
+        let result = DefaultSchemaResolver::new(Some(PathBuf::from("schemas")))
+            .resolve("missing.schema.json")
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SchemaValidationError::SchemaNotFound(id)) if id == "missing.schema.json"
+        ));
+    }
+
+    /// Serves schemas from a map, counting resolve calls per id
+    struct ChainResolver {
+        schemas: HashMap<&'static str, Value>,
+        calls: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[tower_lsp::async_trait]
+    impl SchemaResolver for ChainResolver {
+        async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+            self.calls.lock().unwrap().push(id.to_string());
+            self.schemas
+                .get(id)
+                .cloned()
+                .map(Arc::new)
+                .ok_or_else(|| SchemaValidationError::SchemaNotFound(id.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_reference_chains_once() {
+        let a = serde_json::json!({
+            "properties": {
+                "runtime": { "$ref": "sub/b.schema.json" },
+                "sidecar": { "$ref": "sub/b.schema.json#/properties/port" },
+                "labels": { "enum": [{ "$ref": "data.json" }] }
+            }
+        });
+        let resolver = ChainResolver {
+            schemas: HashMap::from([
+                // relative to b, ie sub/c.schema.json
+                (
+                    "sub/b.schema.json",
+                    serde_json::json!({ "properties": { "port": { "$ref": "c.schema.json" } } }),
+                ),
+                // refers back to b, the cycle must not be followed again
+                (
+                    "sub/c.schema.json",
+                    serde_json::json!({ "type": "integer", "allOf": [{ "$ref": "b.schema.json" }] }),
+                ),
+            ]),
+            calls: Default::default(),
+        };
+
+        let resources = resolve_references(&resolver, &a).await.unwrap();
+
+        let mut uris: Vec<&String> = resources.keys().collect();
+        uris.sort();
+        assert_eq!(
+            uris,
+            vec![
+                "json-schema:///sub/b.schema.json",
+                "json-schema:///sub/c.schema.json"
+            ]
+        );
+        assert_eq!(resolver.calls.lock().unwrap().len(), 2);
+        assert_eq!(
+            resources["json-schema:///sub/c.schema.json"]["type"],
+            "integer"
+        );
+    }
+
+    /// Serves `fast.schema.json` at once and never finishes any other fetch
+    struct HangingResolver;
+
+    #[tower_lsp::async_trait]
+    impl SchemaResolver for HangingResolver {
+        async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+            if id != "https://example.com/fast.schema.json" {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Ok(Arc::new(serde_json::json!({ "type": "object" })))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_reference_is_named_in_error() {
+        let schema = serde_json::json!({
+            "properties": {
+                "runtime": { "$ref": "https://example.com/fast.schema.json" },
+                "ports": { "$ref": "https://example.com/slow.schema.json#/definitions/port" }
+            }
+        });
+
+        let result =
+            resolve_references_within(&HangingResolver, &schema, Duration::from_millis(20)).await;
+
+        let Err(SchemaValidationError::ValidatorCompilationError(message)) = result else {
+            panic!("expected a compilation error, got {:?}", result);
+        };
+        assert!(message.contains("slow.schema.json"), "{}", message);
+        assert!(!message.contains("fast.schema.json"), "{}", message);
+        assert!(message.contains("timed out"), "{}", message);
+    }
+
+    /// Fails every resolve after the first, like a fetch losing network access
+    #[derive(Default)]
+    struct FlakyResolver {
+        fetched: std::sync::atomic::AtomicBool,
+    }
+
+    #[tower_lsp::async_trait]
+    impl SchemaResolver for FlakyResolver {
+        async fn resolve(&self, id: &str) -> ValidationResult<Arc<Value>> {
+            if self.fetched.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                return Err(SchemaValidationError::SchemaNotFound(id.to_string()));
+            }
+            Ok(Arc::new(serde_json::json!({ "title": "remote" })))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_resolver_falls_back_to_stale_copy() {
+        let cache_dir = TempDir::new("cache");
+        let resolver = CachedSchemaResolver::new(FlakyResolver::default(), cache_dir.to_path_buf());
+        let id = "https://example.com/service.schema.json";
+
+        let fetched = resolver.resolve(id).await.unwrap();
+        assert!(resolver.notices(id).await.is_empty());
+
+        let cached = resolver.resolve(id).await.unwrap();
+        assert_eq!(cached, fetched);
+        let notices = resolver.notices(id).await;
+        assert_eq!(notices.len(), 1);
+        assert_eq!(notices[0].severity, Some(DiagnosticSeverity::INFORMATION));
+        assert!(notices[0].message.contains("stale"));
+
+        // nothing cached, the fetch error surfaces
+        assert!(matches!(
+            resolver.resolve("https://example.com/other.json").await,
+            Err(SchemaValidationError::SchemaNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_cache_paths_differ_for_ids_flattening_alike() {
+        let resolver = CachedSchemaResolver::new(FlakyResolver::default(), PathBuf::from("cache"));
+
+        let slash = resolver.cache_path("https://example.com/a/b.json");
+        let underscore = resolver.cache_path("https://example.com/a_b.json");
+        assert_ne!(slash, underscore);
+        assert_eq!(slash, resolver.cache_path("https://example.com/a/b.json"));
+        assert!(slash.to_string_lossy().ends_with("example.com_a_b.json"));
+    }
+
+    #[cfg(feature = "zip")]
+    #[tokio::test]
+    async fn test_bundle_resolver_reads_schemas_by_id() {
+        use std::io::Write;
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, schema) in [
+            ("service.schema.json", r#"{ "title": "service" }"#),
+            ("runtime.schema.json", r#"{ "title": "runtime" }"#),
+        ] {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(schema.as_bytes()).unwrap();
+        }
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let resolver = BundleSchemaResolver::from_bytes(bytes);
+
+        let service = resolver.resolve("service.schema.json").await.unwrap();
+        let runtime = resolver.resolve("runtime.schema.json").await.unwrap();
+        assert_eq!(service["title"], "service");
+        assert_eq!(runtime["title"], "runtime");
+
+        // cached entries are shared
+        let again = resolver.resolve("service.schema.json").await.unwrap();
+        assert!(Arc::ptr_eq(&service, &again));
+
+        assert!(matches!(
+            resolver.resolve("missing.json").await,
+            Err(SchemaValidationError::SchemaNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,23 @@
+use tower_lsp::Client;
+use tower_lsp::lsp_types::{Diagnostic, Url};
+use tracing::warn;
+
+/// Destination of the diagnostics computed for a document
+/// The language server publishes through its `Client`, implement this to receive the
+/// diagnostics elsewhere when embedding the validation engine outside tower-lsp
+#[tower_lsp::async_trait]
+pub trait DiagnosticSink: Send + Sync {
+    /// Replaces every diagnostic previously published for `uri`
+    /// `version` is the document version they were computed for, when known
+    async fn publish(&self, uri: &str, diagnostics: Vec<Diagnostic>, version: Option<i32>);
+}
+
+#[tower_lsp::async_trait]
+impl DiagnosticSink for Client {
+    async fn publish(&self, uri: &str, diagnostics: Vec<Diagnostic>, version: Option<i32>) {
+        match Url::parse(uri) {
+            Ok(uri) => self.publish_diagnostics(uri, diagnostics, version).await,
+            Err(e) => warn!(uri = uri, error = %e, "Not publishing diagnostics for invalid uri"),
+        }
+    }
+}
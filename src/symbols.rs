@@ -0,0 +1,136 @@
+use serde_json::Value;
+use tower_lsp::lsp_types::{DocumentSymbol, Range, SymbolKind};
+use tracing::{instrument, trace};
+
+use crate::document::Document;
+use crate::line_number::PositionEncoding;
+use crate::{cursor, introspection, value_span};
+
+/// Outline of the document: one symbol per object member and array element, nested like
+/// the document. With a schema, each symbol's detail shows the type and description the
+/// schema declares for that node
+#[instrument(skip_all, fields(content_len = file_contents.len()))]
+pub fn document_symbols(json_schema: Option<&Value>, file_contents: &str) -> Vec<DocumentSymbol> {
+    let root = value_span::skip_whitespace(file_contents, 0);
+    children(json_schema, &Document::new(file_contents), root, "")
+}
+
+fn children(
+    json_schema: Option<&Value>,
+    document: &Document,
+    value_start: usize,
+    pointer: &str,
+) -> Vec<DocumentSymbol> {
+    let file_contents = document.text();
+    let entries: Vec<(String, value_span::Span, value_span::Span)> =
+        match file_contents.as_bytes().get(value_start) {
+            Some(b'{') => value_span::members(file_contents, value_start)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|m| (m.key, m.key_span, m.value_span))
+                .collect(),
+            Some(b'[') => value_span::elements(file_contents, value_start)
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(idx, span)| (idx.to_string(), span, span))
+                .collect(),
+            _ => return Vec::new(),
+        };
+
+    entries
+        .into_iter()
+        .map(|(name, key_span, value_span)| {
+            let pointer = format!("{}/{}", pointer, cursor::escape(&name));
+            let detail = json_schema
+                .and_then(|schema| introspection::resolve(schema, &pointer))
+                .and_then(detail);
+            trace!(pointer = %pointer, detail = ?detail, "Built document symbol");
+
+            let children = children(json_schema, document, value_span.0, &pointer);
+            #[allow(deprecated)] // `deprecated` is superseded by tags but has no default
+            DocumentSymbol {
+                name,
+                detail,
+                kind: kind(file_contents.as_bytes()[value_span.0]),
+                tags: None,
+                deprecated: None,
+                range: range(document, (key_span.0, value_span.1)),
+                selection_range: range(document, key_span),
+                children: (!children.is_empty()).then_some(children),
+            }
+        })
+        .collect()
+}
+
+/// `type`, then the `description` (or `title`) of a schema node, eg `object - Container runtime`
+fn detail(schema_node: &Value) -> Option<String> {
+    let kind = match schema_node.get("type") {
+        Some(Value::String(kind)) => Some(kind.clone()),
+        Some(Value::Array(kinds)) => Some(
+            kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(" | "),
+        ),
+        _ => None,
+    };
+    let about = ["description", "title"]
+        .iter()
+        .find_map(|field| schema_node.get(field).and_then(Value::as_str));
+
+    match (kind, about) {
+        (Some(kind), Some(about)) => Some(format!("{} - {}", kind, about)),
+        (Some(kind), None) => Some(kind),
+        (None, about) => about.map(str::to_string),
+    }
+}
+
+/// Symbol kind from the first byte of a value literal
+fn kind(first: u8) -> SymbolKind {
+    match first {
+        b'{' => SymbolKind::OBJECT,
+        b'[' => SymbolKind::ARRAY,
+        b'"' => SymbolKind::STRING,
+        b't' | b'f' => SymbolKind::BOOLEAN,
+        b'n' => SymbolKind::NULL,
+        _ => SymbolKind::NUMBER,
+    }
+}
+
+fn range(document: &Document, (start, end): value_span::Span) -> Range {
+    Range {
+        start: document.to_position(start, PositionEncoding::Utf16),
+        end: document.to_position(end, PositionEncoding::Utf16),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_detail_comes_from_schema() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+        let contents = include_str!("../testing/test_control.json");
+
+        let symbols = document_symbols(Some(&schema), contents);
+        let runtime = symbols.iter().find(|s| s.name == "runtime").unwrap();
+
+        assert_eq!(runtime.kind, SymbolKind::OBJECT);
+        assert_eq!(runtime.detail.as_deref(), Some("object"));
+        assert_eq!(runtime.selection_range.start.line, 3);
+        let nested: Vec<&str> = runtime
+            .children
+            .iter()
+            .flatten()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(nested, vec!["type", "docker"]);
+
+        let without_schema = document_symbols(None, contents);
+        assert!(without_schema.iter().all(|s| s.detail.is_none()));
+    }
+}
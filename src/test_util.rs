@@ -0,0 +1,74 @@
+//! Assertions and fixtures shared by the unit tests of every module
+
+use std::path::{Path, PathBuf};
+
+use tower_lsp::lsp_types::Diagnostic;
+
+/// Fresh directory below the system temp dir, removed with its contents when dropped
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` keeps the directories of different tests apart, the process id those of
+    /// concurrent test runs
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("pur-{}-{}", name, std::process::id()));
+        // left over by a run that panicked
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Asserts some diagnostic starts on `line` (0-based) with `substring` in its message
+#[track_caller]
+pub(crate) fn assert_diagnostic_at(diagnostics: &[Diagnostic], line: u32, substring: &str) {
+    let found = diagnostics
+        .iter()
+        .any(|d| d.range.start.line == line && d.message.contains(substring));
+
+    assert!(
+        found,
+        "no diagnostic on line {} containing {:?}, got:\n{}",
+        line,
+        substring,
+        listing(diagnostics)
+    );
+}
+
+/// Asserts the document produced no diagnostics, listing them otherwise
+#[track_caller]
+pub(crate) fn assert_no_diagnostics(diagnostics: &[Diagnostic]) {
+    assert!(
+        diagnostics.is_empty(),
+        "expected no diagnostics, got:\n{}",
+        listing(diagnostics)
+    );
+}
+
+/// One `line:character message` row per diagnostic, for failure output
+fn listing(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|d| {
+            format!(
+                "  {}:{} {}",
+                d.range.start.line, d.range.start.character, d.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
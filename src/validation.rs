@@ -1,13 +1,236 @@
-use crate::{diagnostic_range, error::SchemaValidationError};
+use crate::{
+    DocumentValidation,
+    config::{Config, SchemaDraft},
+    cursor, diagnostic_range,
+    document::Document,
+    error::{SchemaValidationError, ValidationResult},
+    introspection, json_pointer,
+    keywords::CustomKeywords,
+    line_number::{self, PositionEncoding},
+    resolver::{self, SchemaResolver},
+    value_span,
+};
 
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use jsonschema::JsonType;
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::paths::LocationSegment;
+use tower_lsp::lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag,
+    Location, NumberOrString, Position, Range, Url,
+};
 use tracing::{debug, info, instrument, trace, warn};
 
+/// Compiles a Json Schema, surfacing an invalid schema as an error instead of panicking
+pub(crate) fn compile(json_schema: &serde_json::Value) -> ValidationResult<jsonschema::Validator> {
+    jsonschema::validator_for(json_schema)
+        .map_err(|e| SchemaValidationError::ValidatorCompilationError(e.to_string()))
+}
+
+/// A schema compiled once and shared, eg across threads validating a batch of documents
+/// Cheaper than `validate_liberally`, which compiles the schema on every call
+pub struct CompiledSchema {
+    json_schema: Arc<serde_json::Value>,
+    validator: jsonschema::Validator,
+    // (pointer, name) of every `format` the validator has no check for
+    unknown_formats: Vec<(String, String)>,
+}
+
+impl CompiledSchema {
+    pub fn new(json_schema: impl Into<Arc<serde_json::Value>>) -> ValidationResult<Self> {
+        Self::with_keywords(json_schema, &CustomKeywords::default())
+    }
+
+    /// Compiles `json_schema` validating the extension keywords registered in `keywords`
+    pub fn with_keywords(
+        json_schema: impl Into<Arc<serde_json::Value>>,
+        keywords: &CustomKeywords,
+    ) -> ValidationResult<Self> {
+        Self::with_options(json_schema, keywords, None)
+    }
+
+    /// Like `with_keywords`, compiled as `draft` when set instead of the detected draft
+    pub fn with_options(
+        json_schema: impl Into<Arc<serde_json::Value>>,
+        keywords: &CustomKeywords,
+        draft: Option<SchemaDraft>,
+    ) -> ValidationResult<Self> {
+        Self::with_resources(json_schema, keywords, draft, &HashMap::new())
+    }
+
+    /// Resolves schema `id` and every schema it references through `resolver`, then
+    /// compiles it. A referenced schema failing to resolve is a compilation error naming it
+    pub async fn load(
+        resolver: &dyn SchemaResolver,
+        id: &str,
+        keywords: &CustomKeywords,
+        draft: Option<SchemaDraft>,
+    ) -> ValidationResult<Self> {
+        let json_schema = resolver.resolve(id).await?;
+        let resources = resolver::resolve_references(resolver, &json_schema).await?;
+        Self::with_resources(json_schema, keywords, draft, &resources)
+    }
+
+    /// Like `with_options`, external `$ref`s resolving to the schemas in `resources`,
+    /// keyed by their absolute URI, see `resolver::resolve_references`
+    pub fn with_resources(
+        json_schema: impl Into<Arc<serde_json::Value>>,
+        keywords: &CustomKeywords,
+        draft: Option<SchemaDraft>,
+        resources: &HashMap<String, Arc<serde_json::Value>>,
+    ) -> ValidationResult<Self> {
+        let json_schema = json_schema.into();
+        let resources = resources.iter().map(|(uri, schema)| {
+            (
+                uri.clone(),
+                jsonschema::Resource::from_contents((**schema).clone()),
+            )
+        });
+        let mut options = keywords.apply(jsonschema::options().with_resources(resources));
+        if let Some(draft) = draft {
+            options = options.with_draft(draft.to_jsonschema());
+        }
+        let validator = options
+            .build(&json_schema)
+            .map_err(|e| SchemaValidationError::ValidatorCompilationError(e.to_string()))?;
+
+        let draft = draft
+            .map(SchemaDraft::to_jsonschema)
+            .or_else(|| detect_draft(&json_schema))
+            .unwrap_or_default();
+        let unknown_formats = unsupported_formats(&json_schema, draft);
+        for (pointer, format) in &unknown_formats {
+            warn!(pointer = %pointer, format = %format, "Schema uses an unknown format");
+        }
+
+        Ok(Self {
+            json_schema,
+            validator,
+            unknown_formats,
+        })
+    }
+
+    pub fn json_schema(&self) -> &Arc<serde_json::Value> {
+        &self.json_schema
+    }
+
+    /// A warning for every `format` of the schema no validator exists for, documents are
+    /// never checked against those. Placed at the document start, the schema is another file
+    pub fn unknown_format_notices(&self, schema_id: &str) -> Vec<Diagnostic> {
+        self.unknown_formats
+            .iter()
+            .map(|(pointer, format)| Diagnostic {
+                severity: Some(DiagnosticSeverity::WARNING),
+                message: format!(
+                    "Schema {} uses unknown format \"{}\" at {}, values are not checked against it",
+                    schema_id, format, pointer
+                ),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Same diagnostics as `validate_liberally` with this schema
+    pub fn validate(&self, file_contents: &str) -> ValidationResult<Vec<Diagnostic>> {
+        self.validate_with_config(file_contents, Config::default_ref())
+    }
+
+    /// Same diagnostics as `validate_with_config` with this schema
+    pub fn validate_with_config(
+        &self,
+        file_contents: &str,
+        config: &Config,
+    ) -> ValidationResult<Vec<Diagnostic>> {
+        crate::validate_document(
+            &self.json_schema,
+            file_contents,
+            DocumentValidation {
+                compiled: Some(&self.validator),
+                ..DocumentValidation::new(config)
+            },
+        )
+    }
+
+    /// Schema violations of a document as structured findings
+    /// Fails with `JsonParseError` when the document is not valid JSON
+    pub fn findings(&self, file_contents: &str) -> ValidationResult<Vec<ValidationFinding>> {
+        let json: serde_json::Value = serde_json::from_str(file_contents)?;
+        SchemaValidator::new(&self.json_schema, &json, file_contents)
+            .with_validator(&self.validator)
+            .findings()
+    }
+
+    /// Same diagnostics as `validate_with_schema_id` with this schema
+    pub fn validate_with_schema_id(
+        &self,
+        schema_id: &str,
+        file_contents: &str,
+        config: &Config,
+    ) -> ValidationResult<Vec<Diagnostic>> {
+        crate::validate_document(
+            &self.json_schema,
+            file_contents,
+            DocumentValidation {
+                compiled: Some(&self.validator),
+                schema_id: Some(schema_id),
+                ..DocumentValidation::new(config)
+            },
+        )
+    }
+}
+
+/// Every `jsonschema` error for `instance`, untouched by the diagnostic mapping
+/// For callers needing the full error kind, schema path and failing instance value
+///
+/// Errors are collected before returning since they borrow the compiled validator,
+/// the instance values they hold are copies
+#[instrument(skip_all)]
+pub fn iter_raw_errors<'a>(
+    json_schema: &'a serde_json::Value,
+    instance: &'a serde_json::Value,
+) -> ValidationResult<impl Iterator<Item = jsonschema::ValidationError<'a>>> {
+    let validator = compile(json_schema)?;
+    let errors: Vec<jsonschema::ValidationError<'a>> = validator
+        .iter_errors(instance)
+        .map(jsonschema::ValidationError::to_owned)
+        .collect();
+
+    debug!(
+        error_count = errors.len(),
+        "Collected raw validation errors"
+    );
+    Ok(errors.into_iter())
+}
+
+/// Draft `jsonschema` selects for a schema, from its `$schema` field
+/// Schemas without `$schema` use the latest supported draft, unrecognized meta-schemas give None
+pub fn detect_draft(json_schema: &serde_json::Value) -> Option<jsonschema::Draft> {
+    match jsonschema::Draft::default().detect(json_schema) {
+        jsonschema::Draft::Unknown => None,
+        draft => Some(draft),
+    }
+}
+
 /// Validates JSON against schema and returns diagnostics
 pub struct SchemaValidator<'a> {
     json_schema: &'a serde_json::Value,
     file_as_json: &'a serde_json::Value,
     file_contents: &'a str,
+    // pointer of `file_as_json` within the document, empty for the document root
+    base_pointer: &'a str,
+    config: &'a Config,
+    // byte index of the cursor while the user is typing, see `editing_at`
+    editing_cursor: Option<usize>,
+    // schemas `json_schema` references by URI, see `resolver::resolve_references`
+    resources: Option<&'a HashMap<String, Arc<serde_json::Value>>>,
+    // `json_schema` compiled ahead of time, see `CompiledSchema`
+    compiled: Option<&'a jsonschema::Validator>,
+    // extension keywords checked by embedder handlers
+    keywords: Option<&'a CustomKeywords>,
+    // line index of `file_contents` built by the caller, see `with_document`
+    document: Option<&'a Document<'a>>,
 }
 
 impl<'a> SchemaValidator<'a> {
@@ -20,18 +243,111 @@ impl<'a> SchemaValidator<'a> {
             json_schema,
             file_as_json,
             file_contents,
+            base_pointer: "",
+            config: Config::default_ref(),
+            editing_cursor: None,
+            resources: None,
+            compiled: None,
+            keywords: None,
+            document: None,
         }
     }
 
-    #[instrument(skip(self))]
+    /// Enables the optional validation modes set in `config`
+    pub fn with_config(mut self, config: &'a Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Marks `file_as_json` as the value found at `base_pointer` within `file_contents`
+    /// Error paths are prefixed with it so ranges resolve against the full document
+    pub fn with_base_pointer(mut self, base_pointer: &'a str) -> Self {
+        self.base_pointer = base_pointer;
+        self
+    }
+
+    /// Schemas external `$ref`s point to, keyed by their absolute URI
+    pub fn with_resources(
+        mut self,
+        resources: &'a HashMap<String, Arc<serde_json::Value>>,
+    ) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// Reuses a validator compiled from `json_schema` instead of compiling it again
+    /// It overrides `with_resources`, `with_keywords` and the config's `draft`, whatever
+    /// they are set to: the validator is used as compiled, with its own
+    pub fn with_validator(mut self, compiled: &'a jsonschema::Validator) -> Self {
+        self.compiled = Some(compiled);
+        self
+    }
+
+    /// Validates the extension keywords registered in `keywords` with their handlers
+    /// Ignored with `with_validator`, that validator must have been compiled with them
+    pub fn with_keywords(mut self, keywords: &'a CustomKeywords) -> Self {
+        self.keywords = Some(keywords);
+        self
+    }
+
+    /// Resolves ranges through `document`, the line index of `file_contents` the caller
+    /// already built, instead of indexing the text again
+    pub fn with_document(mut self, document: &'a Document<'a>) -> Self {
+        self.document = Some(document);
+        self
+    }
+
+    /// Editing mode: skips `required` violations of the object the cursor is typing in,
+    /// it is incomplete until the user is done. Other violations are still reported
+    pub fn editing_at(mut self, cursor: Position) -> Self {
+        self.editing_cursor =
+            line_number::to_index(self.file_contents, cursor.line, cursor.character);
+        self
+    }
+
+    /// Returns all schema violations as LSP diagnostics
     pub fn validate(self) -> Result<Vec<Diagnostic>, SchemaValidationError> {
+        Ok(self.collect()?.into_iter().map(Diagnostic::from).collect())
+    }
+
+    /// Returns all schema violations as structured findings for programmatic consumers
+    pub fn findings(self) -> Result<Vec<ValidationFinding>, SchemaValidationError> {
+        Ok(self
+            .collect()?
+            .into_iter()
+            .map(ValidationFinding::from)
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    fn collect(self) -> Result<Vec<ValidationDiagnostic>, SchemaValidationError> {
         // init validator to parse errors
-        // if the below fails.. invalid schema is present (this should not really be something that can
-        // happen. the schemas NEED to be correct for any of this to matter)
+        // if the below fails.. the schema is invalid, eg for a `draft` forced through the config
         trace!("Creating schema validator");
-        let validator = jsonschema::validator_for(self.json_schema)
-            .expect("Internal schema violated: Schema needs to be valid"); // expect since LSP
-        // diagnostics are based on correctness of schema
+        let built;
+        let validator = match self.compiled {
+            Some(compiled) => compiled,
+            None => {
+                let resources = self.resources.into_iter().flatten().map(|(uri, schema)| {
+                    (
+                        uri.clone(),
+                        jsonschema::Resource::from_contents((**schema).clone()),
+                    )
+                });
+                let mut options = jsonschema::options().with_resources(resources);
+                if let Some(draft) = self.config.draft {
+                    options = options.with_draft(draft.to_jsonschema());
+                }
+                let options = match self.keywords {
+                    Some(keywords) => keywords.apply(options),
+                    None => options,
+                };
+                built = options
+                    .build(self.json_schema)
+                    .map_err(|e| SchemaValidationError::ValidatorCompilationError(e.to_string()))?;
+                &built
+            }
+        };
 
         debug!("Schema validator created successfully");
 
@@ -51,14 +367,362 @@ impl<'a> SchemaValidator<'a> {
             );
         }
 
-        let diagnostics = validation_errors
+        // an empty `{}` / `[]` failing for lack of content gets one clear message instead
+        let (empty_root_errors, validation_errors): (Vec<_>, Vec<_>) =
+            validation_errors.into_iter().partition(|e| {
+                self.is_empty_document()
+                    && e.instance_path().as_str().is_empty()
+                    && matches!(
+                        e.kind(),
+                        ValidationErrorKind::Required { .. }
+                            | ValidationErrorKind::MinProperties { .. }
+                            | ValidationErrorKind::MinItems { .. }
+                    )
+            });
+
+        let validation_errors: Vec<_> = validation_errors
+            .into_iter()
+            .filter(|e| !(self.config.allow_unknown_top_level && self.is_root_unknown_key(e)))
+            .filter(|e| !self.is_required_while_editing(e))
+            .filter(|e| !(self.config.env_placeholders && is_placeholder_check(e)))
+            .collect();
+        // every diagnostic's range is resolved against the same document, index it once
+        let indexed;
+        let document = match self.document {
+            Some(document) => document,
+            None => {
+                indexed = Document::new(self.file_contents);
+                &indexed
+            }
+        };
+        let (validation_errors, array_summaries) = match self.config.collapse_array_errors {
+            Some(threshold) => self.collapse_array_errors(validation_errors, threshold, document),
+            None => (validation_errors, Vec::new()),
+        };
+
+        let diagnostics: Vec<ValidationDiagnostic> = validation_errors
             .into_iter()
             // todo.. Add Diagnostic Code for schema validation errors vs json syntax errors.
-            .map(|e| ValidationDiagnostic::new(e, self.file_contents).into())
+            .map(|e| {
+                let diagnostic =
+                    ValidationDiagnostic::new(&e, self.json_schema, self.base_pointer, document);
+                let diagnostic = match &self.config.docs_base_url {
+                    Some(base) => diagnostic.with_docs_link(base, &e),
+                    None => diagnostic,
+                };
+                if self.config.case_insensitive_enums {
+                    diagnostic.with_enum_case_suggestion(&e)
+                } else {
+                    diagnostic
+                }
+            })
             .collect();
 
+        let mut diagnostics = dedup_by_keyword(diagnostics);
+        diagnostics.extend(array_summaries);
+
+        let empty_root_errors: Vec<_> = empty_root_errors
+            .into_iter()
+            .filter(|e| !self.is_required_while_editing(e))
+            .collect();
+        if !empty_root_errors.is_empty() {
+            diagnostics.insert(
+                0,
+                self.empty_document_diagnostic(&empty_root_errors, document),
+            );
+        }
+
         Ok(diagnostics)
     }
+
+    /// Splits off array elements failing with the same message more than `threshold`
+    /// times, returning the remaining errors and one summary diagnostic per such array
+    fn collapse_array_errors<'e>(
+        &self,
+        errors: Vec<jsonschema::ValidationError<'e>>,
+        threshold: usize,
+        document: &Document,
+    ) -> (
+        Vec<jsonschema::ValidationError<'e>>,
+        Vec<ValidationDiagnostic>,
+    ) {
+        // (array pointer, message) of an error on an array element, numeric object keys
+        // look the same in a pointer so the parent is checked to really be an array
+        let group_of = |error: &jsonschema::ValidationError| {
+            let pointer = canonical_pointer(error.instance_path());
+            let (array, index) = pointer.rsplit_once('/')?;
+            index.parse::<usize>().ok()?;
+            self.file_as_json.pointer(array)?.as_array()?;
+            Some((array.to_string(), describe(error)))
+        };
+
+        let mut counts: HashMap<(String, String), usize> = HashMap::new();
+        for error in &errors {
+            if let Some(group) = group_of(error) {
+                *counts.entry(group).or_default() += 1;
+            }
+        }
+
+        let mut summaries: Vec<ValidationDiagnostic> = Vec::new();
+        let mut kept = Vec::new();
+        for error in errors {
+            let Some(group) = group_of(&error) else {
+                kept.push(error);
+                continue;
+            };
+            // the first element of a collapsed group stands for all of them, the rest are dropped
+            let count = match counts.get(&group) {
+                Some(&count) if count > threshold => count,
+                Some(_) => {
+                    kept.push(error);
+                    continue;
+                }
+                None => continue,
+            };
+            counts.remove(&group);
+
+            let instance_path = format!("{}{}", self.base_pointer, group.0);
+            debug!(pointer = %instance_path, count, "Collapsing array element errors");
+            summaries.push(ValidationDiagnostic {
+                error_message: format!(
+                    "{} elements fail the same constraint, eg: {}",
+                    count,
+                    describe(&error)
+                ),
+                range: diagnostic_range::guarded(&instance_path, || {
+                    json_pointer::value_range_in(&instance_path, document)
+                }),
+                kind: ValidationKind::from_error(&error),
+                severity: DiagnosticSeverity::ERROR,
+                suggestion: None,
+                docs_link: None,
+                instance_path,
+            });
+        }
+
+        (kept, summaries)
+    }
+
+    /// Whether the whole document is `{}` or `[]`
+    fn is_empty_document(&self) -> bool {
+        self.base_pointer.is_empty()
+            && match self.file_as_json {
+                serde_json::Value::Object(object) => object.is_empty(),
+                serde_json::Value::Array(items) => items.is_empty(),
+                _ => false,
+            }
+    }
+
+    /// Single diagnostic on the opening brace of an empty document the schema wants content in
+    fn empty_document_diagnostic(
+        &self,
+        errors: &[jsonschema::ValidationError],
+        document: &Document,
+    ) -> ValidationDiagnostic {
+        let required: Vec<String> = errors
+            .iter()
+            .filter_map(|e| match e.kind() {
+                ValidationErrorKind::Required { property } => Some(property.to_string()),
+                _ => None,
+            })
+            .collect();
+        let error_message = if required.is_empty() {
+            "Document is empty, the schema requires content".to_string()
+        } else {
+            format!(
+                "Document is empty, the schema requires {}",
+                required.join(", ")
+            )
+        };
+        debug!(message = %error_message, "Document is empty");
+
+        let brace = value_span::skip_whitespace(self.file_contents, 0);
+        ValidationDiagnostic {
+            instance_path: String::new(),
+            error_message,
+            range: Range {
+                start: document.to_position(brace, PositionEncoding::Utf16),
+                end: document.to_position(brace + 1, PositionEncoding::Utf16),
+            },
+            kind: ValidationKind::from_error(&errors[0]),
+            severity: DiagnosticSeverity::ERROR,
+            suggestion: None,
+            docs_link: None,
+        }
+    }
+
+    /// Whether `error` is a missing property of the innermost object containing the cursor
+    fn is_required_while_editing(&self, error: &jsonschema::ValidationError) -> bool {
+        let Some(cursor) = self.editing_cursor else {
+            return false;
+        };
+        if !matches!(error.kind(), ValidationErrorKind::Required { .. }) {
+            return false;
+        }
+
+        let object_pointer = format!(
+            "{}{}",
+            self.base_pointer,
+            canonical_pointer(error.instance_path())
+        );
+        let contains = |(start, end): value_span::Span| start <= cursor && cursor < end;
+        let Some(span) = value_span::locate(&object_pointer, self.file_contents) else {
+            return false;
+        };
+        if !contains(span) {
+            return false;
+        }
+
+        // a nested object or array around the cursor is the one being edited instead
+        let bytes = self.file_contents.as_bytes();
+        let editing_nested = value_span::members(self.file_contents, span.0)
+            .unwrap_or_default()
+            .into_iter()
+            .any(|member| {
+                matches!(bytes.get(member.value_span.0), Some(b'{' | b'['))
+                    && contains(member.value_span)
+            });
+        if !editing_nested {
+            trace!(pointer = %object_pointer, "Skipping required error of the edited object");
+        }
+        !editing_nested
+    }
+
+    /// Whether `error` reports a key the schema does not allow on the document root object
+    fn is_root_unknown_key(&self, error: &jsonschema::ValidationError) -> bool {
+        // extra keys rejected by a bare `additionalProperties: false` carry the parent's path too
+        ValidationKind::from_error(error).keyword() == "additionalProperties"
+            && self.base_pointer.is_empty()
+            && canonical_pointer(error.instance_path()).is_empty()
+    }
+}
+
+/// Keeps one diagnostic per (instance pointer, keyword), eg the same `anyOf` reached
+/// through a `$ref` and inline, with the longest (most specific) message of the group
+/// `required` and `additionalProperties` name a different key in each message, they are
+/// all kept
+fn dedup_by_keyword(diagnostics: Vec<ValidationDiagnostic>) -> Vec<ValidationDiagnostic> {
+    let mut kept: Vec<ValidationDiagnostic> = Vec::with_capacity(diagnostics.len());
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+
+    for diagnostic in diagnostics {
+        if matches!(
+            diagnostic.kind,
+            ValidationKind::Required | ValidationKind::AdditionalProperties
+        ) {
+            kept.push(diagnostic);
+            continue;
+        }
+
+        let key = (
+            diagnostic.instance_path.clone(),
+            diagnostic.kind.keyword().to_string(),
+        );
+        match index.get(&key) {
+            Some(&idx) => {
+                trace!(pointer = %key.0, keyword = %key.1, "Dropping duplicate diagnostic");
+                if diagnostic.error_message.len() > kept[idx].error_message.len() {
+                    kept[idx] = diagnostic;
+                }
+            }
+            None => {
+                index.insert(key, kept.len());
+                kept.push(diagnostic);
+            }
+        }
+    }
+
+    kept
+}
+
+/// Whether `error` is a `pattern`, `format` or `enum` check of a `${...}` placeholder value,
+/// which only gets its real value when the environment is substituted
+fn is_placeholder_check(error: &jsonschema::ValidationError) -> bool {
+    let is_placeholder = error
+        .instance()
+        .as_str()
+        .and_then(|value| value.strip_prefix("${")?.strip_suffix('}'))
+        .is_some_and(|name| !name.is_empty() && !name.contains(['{', '}']));
+
+    is_placeholder
+        && matches!(
+            error.kind(),
+            ValidationErrorKind::Pattern { .. }
+                | ValidationErrorKind::Format { .. }
+                | ValidationErrorKind::Enum { .. }
+        )
+}
+
+/// Normalized category of a schema violation, derived from the `jsonschema` error kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationKind {
+    Type,
+    Required,
+    Enum,
+    Pattern,
+    MinLength,
+    AdditionalProperties,
+    /// Any other keyword, holds the failing keyword name from the schema path
+    Other(String),
+}
+
+impl ValidationKind {
+    pub fn from_error(error: &jsonschema::ValidationError) -> Self {
+        match error.kind() {
+            ValidationErrorKind::Type { .. } => ValidationKind::Type,
+            ValidationErrorKind::Required { .. } => ValidationKind::Required,
+            ValidationErrorKind::Enum { .. } => ValidationKind::Enum,
+            ValidationErrorKind::Pattern { .. } => ValidationKind::Pattern,
+            ValidationErrorKind::MinLength { .. } => ValidationKind::MinLength,
+            ValidationErrorKind::AdditionalProperties { .. } => {
+                ValidationKind::AdditionalProperties
+            }
+            // the schema path continues into the keyword the key name failed
+            ValidationErrorKind::PropertyNames { .. } => {
+                ValidationKind::Other("propertyNames".to_string())
+            }
+            // schema path ends in the keyword that failed, eg /properties/port/maximum
+            _ => ValidationKind::Other(
+                error
+                    .schema_path()
+                    .as_str()
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Schema keyword this kind of violation belongs to, eg `additionalProperties`
+    pub fn keyword(&self) -> &str {
+        match self {
+            ValidationKind::Type => "type",
+            ValidationKind::Required => "required",
+            ValidationKind::Enum => "enum",
+            ValidationKind::Pattern => "pattern",
+            ValidationKind::MinLength => "minLength",
+            ValidationKind::AdditionalProperties => "additionalProperties",
+            ValidationKind::Other(keyword) => keyword,
+        }
+    }
+}
+
+/// Structured schema violation, the non-LSP counterpart of a validation diagnostic
+#[derive(Debug, Clone)]
+pub struct ValidationFinding {
+    pub instance_path: String,
+    pub message: String,
+    /// 0-based like every LSP range, see `to_one_based`
+    pub range: Range,
+    pub kind: ValidationKind,
+}
+
+impl ValidationFinding {
+    /// 1-based line and column where the finding starts, as shown to users
+    pub fn to_one_based(&self) -> (u32, u32) {
+        line_number::to_one_based(self.range.start)
+    }
 }
 
 /// Wrapper for creating validation diagnostics
@@ -66,13 +730,32 @@ pub struct ValidationDiagnostic {
     instance_path: String,
     error_message: String,
     range: Range,
+    kind: ValidationKind,
+    severity: DiagnosticSeverity,
+    // replacement value offered as a fix-it, carried in the diagnostic data
+    suggestion: Option<serde_json::Value>,
+    // documentation of the failing schema node, see `Config::docs_base_url`
+    docs_link: Option<Url>,
 }
 
 impl ValidationDiagnostic {
-    #[instrument(skip(error, file_contents), fields(instance_path = %error.instance_path()))]
-    pub fn new(error: jsonschema::ValidationError, file_contents: &str) -> Self {
-        let instance_path = error.instance_path().to_string();
-        let error_message = error.to_string();
+    #[instrument(skip(error, json_schema, document), fields(instance_path = %error.instance_path()))]
+    pub fn new(
+        error: &jsonschema::ValidationError,
+        json_schema: &serde_json::Value,
+        base_pointer: &str,
+        document: &Document,
+    ) -> Self {
+        let instance_path = format!(
+            "{}{}",
+            base_pointer,
+            canonical_pointer(error.instance_path())
+        );
+        let description = contains_message(json_schema, error).unwrap_or_else(|| describe(error));
+        let error_message = match subschema_title(json_schema, error) {
+            Some(title) => format!("[{}] {}", title, description),
+            None => description,
+        };
 
         trace!(
             path = %instance_path,
@@ -83,12 +766,528 @@ impl ValidationDiagnostic {
         // TODO FOR RANGE -> take Json pointer from
         // TODO create function to return File Position from JsonPointer/find crate
         // e.instance_path() -> And map to a Range on the original file contents
-        let range = diagnostic_range::from_pointer(error.instance_path().as_str(), file_contents);
+        let precise_range = match error.kind() {
+            // the error points at the parent object, underline the unexpected key instead
+            ValidationErrorKind::AdditionalProperties { unexpected } => {
+                unexpected.first().and_then(|key| {
+                    let key_pointer = format!("{}/{}", instance_path, cursor::escape(key));
+                    diagnostic_range::try_guarded(&key_pointer, || {
+                        json_pointer::key_range_in(&key_pointer, document)
+                    })
+                })
+            }
+            // the key name itself failed, the nested error's instance is that key
+            ValidationErrorKind::PropertyNames { error } => {
+                error.instance().as_str().and_then(|key| {
+                    let key_pointer = format!("{}/{}", instance_path, cursor::escape(key));
+                    diagnostic_range::try_guarded(&key_pointer, || {
+                        json_pointer::key_range_in(&key_pointer, document)
+                    })
+                })
+            }
+            // a bare `null` document where an object belongs, underline the literal itself
+            ValidationErrorKind::Type { kind }
+                if instance_path.is_empty()
+                    && error.instance().is_null()
+                    && match kind {
+                        TypeKind::Single(ty) => *ty == JsonType::Object,
+                        TypeKind::Multiple(types) => types.contains(JsonType::Object),
+                    } =>
+            {
+                json_pointer::value_range_in("", document)
+            }
+            // length and contains keywords are about the whole array/string literal
+            ValidationErrorKind::MinItems { .. }
+            | ValidationErrorKind::MaxItems { .. }
+            | ValidationErrorKind::Contains
+            | ValidationErrorKind::MinLength { .. }
+            | ValidationErrorKind::MaxLength { .. } => {
+                diagnostic_range::try_guarded(&instance_path, || {
+                    json_pointer::value_range_in(&instance_path, document)
+                })
+            }
+            _ => None,
+        };
+        let range = precise_range
+            .unwrap_or_else(|| diagnostic_range::from_pointer_in(&instance_path, document));
 
         Self {
             instance_path,
             error_message,
             range,
+            kind: ValidationKind::from_error(error),
+            severity: DiagnosticSeverity::ERROR,
+            suggestion: None,
+            docs_link: None,
+        }
+    }
+
+    /// Links the documentation of the failing schema node: `base` with the error's schema
+    /// path as fragment, eg https://docs.example.com/service#/properties/port/type
+    pub fn with_docs_link(mut self, base: &str, error: &jsonschema::ValidationError) -> Self {
+        let link = format!(
+            "{}#{}",
+            base.trim_end_matches('#'),
+            error.schema_path().as_str()
+        );
+        match Url::parse(&link) {
+            Ok(url) => self.docs_link = Some(url),
+            Err(e) => debug!(link = %link, error = %e, "Invalid documentation link"),
+        }
+        self
+    }
+
+    /// Downgrades string enum violations that only differ in case to a warning,
+    /// suggesting the correctly cased enum value
+    pub fn with_enum_case_suggestion(mut self, error: &jsonschema::ValidationError) -> Self {
+        let ValidationErrorKind::Enum { options } = error.kind() else {
+            return self;
+        };
+        let Some(value) = error.instance().as_str() else {
+            return self;
+        };
+
+        let matching = options
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(serde_json::Value::as_str)
+            .find(|option| option.eq_ignore_ascii_case(value));
+
+        if let Some(option) = matching {
+            trace!(value, option, "Found case-insensitive enum match");
+            self.error_message = format!(
+                "\"{}\" only matches enum value \"{}\" case-insensitively",
+                value, option
+            );
+            self.severity = DiagnosticSeverity::WARNING;
+            self.suggestion = Some(option.into());
+        }
+
+        self
+    }
+}
+
+/// Canonical RFC 6901 form of an instance location, every key segment escaped exactly once
+/// Range resolution unescapes segments again, so both sides agree on keys with `/` or `~`
+pub(crate) fn canonical_pointer(location: &jsonschema::paths::Location) -> String {
+    location
+        .iter()
+        .map(|segment| match segment {
+            LocationSegment::Property(key) => format!("/{}", cursor::escape(&key)),
+            LocationSegment::Index(idx) => format!("/{}", idx),
+        })
+        .collect()
+}
+
+/// Warnings for present properties whose schema node is marked `"deprecated": true`
+/// Tagged DEPRECATED so editors strike the key through
+#[instrument(skip_all)]
+pub fn deprecated_properties(
+    json_schema: &serde_json::Value,
+    file_as_json: &serde_json::Value,
+    document: &Document,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    collect_deprecated(json_schema, file_as_json, "", document, &mut diagnostics);
+
+    if !diagnostics.is_empty() {
+        debug!(count = diagnostics.len(), "Found deprecated properties");
+    }
+    diagnostics
+}
+
+fn collect_deprecated(
+    json_schema: &serde_json::Value,
+    value: &serde_json::Value,
+    pointer: &str,
+    document: &Document,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let children: Vec<(String, &serde_json::Value)> = match value {
+        serde_json::Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| (format!("{}/{}", pointer, cursor::escape(key)), child))
+            .collect(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(idx, child)| (format!("{}/{}", pointer, idx), child))
+            .collect(),
+        _ => return,
+    };
+
+    for (child_pointer, child) in children {
+        let deprecated = introspection::resolve(json_schema, &child_pointer)
+            .and_then(|node| node.get("deprecated"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+
+        // array elements have no key to strike through
+        if deprecated && value.is_object() {
+            trace!(pointer = %child_pointer, "Property is deprecated");
+            let range = diagnostic_range::guarded(&child_pointer, || {
+                json_pointer::key_range_in(&child_pointer, document)
+            });
+            let key = child_pointer.rsplit('/').next().unwrap_or_default();
+            diagnostics.push(Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::WARNING),
+                tags: Some(vec![DiagnosticTag::DEPRECATED]),
+                source: Some(child_pointer.clone()),
+                message: format!("\"{}\" is deprecated", introspection::unescape(key)),
+                ..Default::default()
+            });
+        }
+
+        collect_deprecated(json_schema, child, &child_pointer, document, diagnostics);
+    }
+}
+
+/// Warnings for integer literals too large to be represented exactly
+///
+/// Documents are parsed without serde_json's `arbitrary_precision`: integers fitting
+/// i64/u64 are exact, larger ones become the nearest f64. Validation then sees that
+/// rounded value, so a large ID is flagged instead of silently checked as another number
+#[instrument(skip_all)]
+pub fn precision_loss(file_as_json: &serde_json::Value, document: &Document) -> Vec<Diagnostic> {
+    let file_contents = document.text();
+    let mut diagnostics = Vec::new();
+
+    introspection::walk(file_as_json, (), |pointer, _, value, ()| {
+        let serde_json::Value::Number(number) = value else {
+            return Some(());
+        };
+        if !number.is_f64() {
+            return None;
+        }
+        let (start, end) = value_span::locate(pointer, file_contents)?;
+        let literal = &file_contents[start..end];
+        let is_integer_literal = literal
+            .strip_prefix('-')
+            .unwrap_or(literal)
+            .bytes()
+            .all(|b| b.is_ascii_digit());
+        // `-0` is parsed as a float too, but loses nothing
+        let fits = literal
+            .parse::<i128>()
+            .is_ok_and(|n| i64::try_from(n).is_ok() || u64::try_from(n).is_ok());
+        if !is_integer_literal || fits {
+            return None;
+        }
+
+        trace!(pointer = %pointer, literal, "Integer exceeds exact range");
+        diagnostics.push(Diagnostic {
+            range: Range {
+                start: document.to_position(start, PositionEncoding::Utf16),
+                end: document.to_position(end, PositionEncoding::Utf16),
+            },
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some(pointer.to_string()),
+            message: format!(
+                "{} is too large to represent exactly and is validated as {}",
+                literal, number
+            ),
+            ..Default::default()
+        });
+        None
+    });
+
+    diagnostics
+}
+
+/// Whether `jsonschema` checks values of `format` in schemas of `draft`
+/// It does not know every format in every draft, eg `uuid` only from 2019-09 on
+pub fn format_supported(format: &str, draft: jsonschema::Draft) -> bool {
+    jsonschema::options()
+        .with_draft(draft)
+        .should_validate_formats(true)
+        .should_ignore_unknown_formats(false)
+        .build(&serde_json::json!({ "format": format }))
+        .is_ok()
+}
+
+/// (pointer, name) of every `format` in `json_schema` that `format_supported` rejects
+fn unsupported_formats(
+    json_schema: &serde_json::Value,
+    draft: jsonschema::Draft,
+) -> Vec<(String, String)> {
+    let mut unsupported = Vec::new();
+    introspection::visit_schema(json_schema, |pointer, keyword, value| {
+        if let Some(format) = value.as_str().filter(|_| keyword == "format")
+            && !format_supported(format, draft)
+        {
+            unsupported.push((pointer.to_owned(), format.to_owned()));
+        }
+    });
+    unsupported
+}
+
+/// Warnings on every `format` of a schema document naming a format no validator exists for,
+/// instances are never checked against those. `schema_contents` is the schema's own text
+#[instrument(skip_all)]
+pub fn unknown_formats(json_schema: &serde_json::Value, schema_contents: &str) -> Vec<Diagnostic> {
+    let draft = detect_draft(json_schema).unwrap_or_default();
+    schema_warnings(json_schema, schema_contents, |keyword, value| {
+        let format = value.as_str().filter(|_| keyword == "format")?;
+        (!format_supported(format, draft)).then(|| {
+            format!(
+                "Unknown format \"{}\", values are not checked against it",
+                format
+            )
+        })
+    })
+}
+
+/// Warnings on every local `$ref` of a schema document pointing at no node of it,
+/// eg a typo in `#/definitions/Fooo`. `schema_contents` is the schema's own text
+#[instrument(skip_all)]
+pub fn dangling_refs(json_schema: &serde_json::Value, schema_contents: &str) -> Vec<Diagnostic> {
+    schema_warnings(json_schema, schema_contents, |keyword, value| {
+        let reference = value.as_str().filter(|_| keyword == "$ref")?;
+        introspection::is_dangling_ref(json_schema, reference)
+            .then(|| format!("$ref \"{}\" does not resolve to a schema node", reference))
+    })
+}
+
+/// A warning on every keyword of a schema document `check` returns a message for,
+/// given the keyword's name and value
+fn schema_warnings(
+    json_schema: &serde_json::Value,
+    schema_contents: &str,
+    check: impl Fn(&str, &serde_json::Value) -> Option<String>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    introspection::visit_schema(json_schema, |pointer, keyword, value| {
+        let Some(message) = check(keyword, value) else {
+            return;
+        };
+        trace!(pointer = %pointer, message = %message, "Schema warning");
+        diagnostics.push(Diagnostic {
+            range: diagnostic_range::from_pointer(pointer, schema_contents),
+            severity: Some(DiagnosticSeverity::WARNING),
+            source: Some(pointer.to_string()),
+            message,
+            ..Default::default()
+        });
+    });
+
+    diagnostics
+}
+
+/// Links every diagnostic nested under another one to its closest failing ancestor
+/// as related information, so editors show the cascading error's root cause
+/// Nesting is read from the instance path each validation diagnostic carries as `source`
+#[instrument(skip(diagnostics), fields(count = diagnostics.len()))]
+pub fn link_related(diagnostics: &mut [Diagnostic], uri: &Url) {
+    let causes: Vec<(String, Range, String)> = diagnostics
+        .iter()
+        .filter_map(|d| Some((d.source.clone()?, d.range, d.message.clone())))
+        .collect();
+
+    for diagnostic in diagnostics.iter_mut() {
+        let Some(pointer) = diagnostic.source.as_deref() else {
+            continue;
+        };
+
+        let ancestor = causes
+            .iter()
+            .filter(|(cause, _, _)| is_ancestor(cause, pointer))
+            .max_by_key(|(cause, _, _)| cause.len());
+
+        if let Some((cause, range, message)) = ancestor {
+            trace!(pointer, cause = %cause, "Linking diagnostic to failing ancestor");
+            diagnostic.related_information = Some(vec![DiagnosticRelatedInformation {
+                location: Location::new(uri.clone(), *range),
+                message: message.clone(),
+            }]);
+        }
+    }
+}
+
+/// Information confirming a document has no problems, see `Config::report_valid_documents`
+/// Names the schema by id when known, its `title` otherwise
+pub fn document_valid_diagnostic(
+    json_schema: &serde_json::Value,
+    schema_id: Option<&str>,
+) -> Diagnostic {
+    let name = schema_id
+        .or_else(|| json_schema.get("title").and_then(serde_json::Value::as_str))
+        .unwrap_or("the schema");
+
+    Diagnostic {
+        range: Range::new(Position::new(0, 0), Position::new(0, 1)),
+        severity: Some(DiagnosticSeverity::INFORMATION),
+        message: format!("Document valid against {}", name),
+        ..Default::default()
+    }
+}
+
+/// Marks diagnostics with the id of the schema they were validated against
+/// The id prefixes the message and is merged into `data` as `schema`, next to any suggestion
+#[instrument(skip(diagnostics), fields(count = diagnostics.len()))]
+pub fn tag_schema(diagnostics: &mut [Diagnostic], schema_id: &str) {
+    for diagnostic in diagnostics.iter_mut() {
+        diagnostic.message = format!("{}: {}", schema_id, diagnostic.message);
+        record_schema(diagnostic, schema_id);
+    }
+}
+
+/// Merges `schema_id` into the diagnostic's `data` as `schema`, leaving the message as is
+/// for diagnostics already naming the schema, like `document_valid_diagnostic`
+pub fn record_schema(diagnostic: &mut Diagnostic, schema_id: &str) {
+    match diagnostic.data.get_or_insert_with(|| serde_json::json!({})) {
+        serde_json::Value::Object(data) => {
+            data.insert("schema".to_owned(), schema_id.into());
+        }
+        other => warn!(data = %other, "Diagnostic data is not an object, schema not recorded"),
+    }
+}
+
+/// Whether `ancestor` is a strict parent pointer of `pointer`, segment-wise
+/// The root is nobody's ancestor, every diagnostic would link to a root error otherwise
+fn is_ancestor(ancestor: &str, pointer: &str) -> bool {
+    !ancestor.is_empty()
+        && pointer
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.len() > 1 && rest.starts_with('/'))
+}
+
+/// `title` of the named subschema holding the failing keyword, eg DockerRuntime
+/// The root schema's title is skipped, it would prefix every top-level message alike
+fn subschema_title<'a>(
+    json_schema: &'a serde_json::Value,
+    error: &jsonschema::ValidationError,
+) -> Option<&'a str> {
+    let schema_path = error.schema_path().as_str();
+    // the last segment is the keyword itself, its parent is the node that declares it
+    let (node_path, _keyword) = schema_path.rsplit_once('/')?;
+    if node_path.is_empty() {
+        return None;
+    }
+
+    introspection::resolve_schema_path(json_schema, node_path)?
+        .get("title")?
+        .as_str()
+}
+
+/// Human readable message for a validation error
+/// Keywords whose `jsonschema` message is cryptic get a dedicated explanation
+fn describe(error: &jsonschema::ValidationError) -> String {
+    match error.kind() {
+        ValidationErrorKind::OneOfNotValid { context }
+        | ValidationErrorKind::OneOfMultipleValid { context } => {
+            // one error list per branch, a branch matched when it produced no errors
+            let matched: Vec<String> = context
+                .iter()
+                .enumerate()
+                .filter(|(_, errors)| errors.is_empty())
+                .map(|(idx, _)| format!("#{}", idx))
+                .collect();
+
+            let mut message = format!(
+                "matched {} of {} oneOf branches (expected exactly 1)",
+                matched.len(),
+                context.len()
+            );
+            if matched.len() > 1 {
+                message.push_str(&format!(": branches {}", matched.join(", ")));
+            }
+            message
+        }
+        ValidationErrorKind::MultipleOf { multiple_of } => {
+            // whole divisors print without a trailing .0, like they are written in schemas
+            let divisor = if multiple_of.fract() == 0.0 && multiple_of.abs() < i64::MAX as f64 {
+                (*multiple_of as i64).to_string()
+            } else {
+                multiple_of.to_string()
+            };
+            format!("{} is not a multiple of {}", error.instance(), divisor)
+        }
+        ValidationErrorKind::Not { schema } => {
+            match schema.get("title").and_then(serde_json::Value::as_str) {
+                Some(title) => format!("value must NOT match the forbidden schema \"{}\"", title),
+                None => "value must NOT match the forbidden schema".to_string(),
+            }
+        }
+        ValidationErrorKind::Type { kind } if expects_integer(kind) && is_fractional(error) => {
+            "expected integer, got a number with a fractional part".to_string()
+        }
+        _ => error.to_string(),
+    }
+}
+
+/// Explains a `contains` failure with how many elements match the contained schema,
+/// against the `minContains` / `maxContains` bounds declared next to it
+/// None when the schema node can't be resolved or the contained schema doesn't compile alone
+fn contains_message(
+    json_schema: &serde_json::Value,
+    error: &jsonschema::ValidationError,
+) -> Option<String> {
+    if !matches!(error.kind(), ValidationErrorKind::Contains) {
+        return None;
+    }
+
+    let (node_path, _keyword) = error.schema_path().as_str().rsplit_once('/')?;
+    let node = introspection::resolve_schema_path(json_schema, node_path)?;
+    let contained = node.get("contains")?;
+    let elements = error.instance().as_array()?;
+
+    let validator = jsonschema::validator_for(contained).ok()?;
+    let matched = elements.iter().filter(|e| validator.is_valid(e)).count();
+    let min = node.get("minContains").and_then(serde_json::Value::as_u64);
+    let max = node.get("maxContains").and_then(serde_json::Value::as_u64);
+    let shape = match contained.get("title").and_then(serde_json::Value::as_str) {
+        Some(title) => format!("the required shape \"{}\"", title),
+        None => "the required shape".to_string(),
+    };
+    trace!(matched, ?min, ?max, "Counted elements matching contains");
+
+    let message = match (min, max) {
+        (_, Some(max)) if matched as u64 > max => format!(
+            "{} of {} elements match {}, at most {} allowed",
+            matched,
+            elements.len(),
+            shape,
+            max
+        ),
+        (Some(min), _) if matched > 0 || min > 1 => format!(
+            "{} of {} elements match {}, at least {} required",
+            matched,
+            elements.len(),
+            shape,
+            min
+        ),
+        _ => format!("no element matches {}", shape),
+    };
+    Some(message)
+}
+
+/// True when the type keyword allows integers but not arbitrary numbers
+fn expects_integer(kind: &TypeKind) -> bool {
+    match kind {
+        TypeKind::Single(ty) => *ty == JsonType::Integer,
+        TypeKind::Multiple(types) => {
+            types.contains(JsonType::Integer) && !types.contains(JsonType::Number)
+        }
+    }
+}
+
+fn is_fractional(error: &jsonschema::ValidationError) -> bool {
+    error
+        .instance()
+        .as_f64()
+        .is_some_and(|number| number.fract() != 0.0)
+}
+
+impl From<ValidationDiagnostic> for ValidationFinding {
+    fn from(diag: ValidationDiagnostic) -> Self {
+        ValidationFinding {
+            instance_path: diag.instance_path,
+            message: diag.error_message,
+            range: diag.range,
+            kind: diag.kind,
         }
     }
 }
@@ -96,11 +1295,946 @@ impl ValidationDiagnostic {
 impl From<ValidationDiagnostic> for Diagnostic {
     fn from(diag: ValidationDiagnostic) -> Self {
         Diagnostic {
-            severity: Some(DiagnosticSeverity::ERROR),
+            severity: Some(diag.severity),
             message: format!("Path {}, Error: {}", diag.instance_path, diag.error_message),
             range: diag.range,
             source: Some(diag.instance_path),
+            data: diag
+                .suggestion
+                .map(|suggestion| serde_json::json!({ "suggestion": suggestion })),
+            // editors render the link on the code, so the keyword comes along with it
+            code: diag
+                .docs_link
+                .is_some()
+                .then(|| NumberOrString::String(diag.kind.keyword().to_string())),
+            code_description: diag.docs_link.map(|href| CodeDescription { href }),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn findings_for(instance: serde_json::Value) -> Vec<ValidationFinding> {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["port"],
+            "properties": {
+                "port": { "type": "integer" }
+            }
+        });
+        let contents = instance.to_string();
+
+        SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .expect("schema is valid")
+    }
+
+    #[test]
+    fn test_type_error_maps_to_type_kind() {
+        let findings = findings_for(serde_json::json!({ "port": "8080" }));
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, ValidationKind::Type);
+        assert_eq!(findings[0].instance_path, "/port");
+    }
+
+    #[test]
+    fn test_case_insensitive_enum_match_is_a_warning() {
+        let schema = serde_json::json!({
+            "properties": { "protocol": { "enum": ["tcp", "udp"] } }
+        });
+        let instance = serde_json::json!({ "protocol": "TCP" });
+        let contents = instance.to_string();
+        let config = Config {
+            case_insensitive_enums: true,
+            ..Default::default()
+        };
+
+        let diagnostics = SchemaValidator::new(&schema, &instance, &contents)
+            .with_config(&config)
+            .validate()
+            .unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diagnostics[0].data,
+            Some(serde_json::json!({ "suggestion": "tcp" }))
+        );
+
+        // without the mode it stays a hard error
+        let diagnostics = SchemaValidator::new(&schema, &instance, &contents)
+            .validate()
+            .unwrap();
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].data, None);
+    }
+
+    #[test]
+    fn test_additional_property_range_targets_extra_key() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+        let contents = include_str!("../testing/test_additional_property.json");
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+        let finding = findings
+            .iter()
+            .find(|f| f.kind == ValidationKind::AdditionalProperties)
+            .expect("additionalProperties finding");
+
+        let line = contents
+            .lines()
+            .nth(finding.range.start.line as usize)
+            .unwrap();
+        let (start, end) = (
+            finding.range.start.character as usize,
+            finding.range.end.character as usize,
+        );
+        assert_eq!(&line[start..end], r#""ocker""#);
+    }
+
+    #[test]
+    fn test_allow_unknown_top_level_keeps_nested_keys_strict() {
+        let schema = serde_json::json!({
+            "additionalProperties": false,
+            "properties": {
+                "runtime": {
+                    "additionalProperties": false,
+                    "properties": { "image": { "type": "string" } }
+                }
+            }
+        });
+        let instance = serde_json::json!({
+            "x-experimental": true,
+            "runtime": { "imgae": "nginx" }
+        });
+        let contents = instance.to_string();
+        let config = Config {
+            allow_unknown_top_level: true,
+            ..Default::default()
+        };
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .with_config(&config)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, ValidationKind::AdditionalProperties);
+        assert_eq!(findings[0].instance_path, "/runtime");
+
+        // without the mode the root key is reported as well
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+        assert_eq!(findings.len(), 2);
+    }
+
+    #[test]
+    fn test_env_placeholders_satisfy_string_checks() {
+        let schema = serde_json::json!({
+            "properties": {
+                "image": { "type": "string", "pattern": "^[a-z]+:[0-9.]+$" },
+                "mode": { "enum": ["dev", "prod"] },
+                "port": { "type": "integer" },
+                "host": { "type": "string", "pattern": "^[a-z.]+$" }
+            }
+        });
+        let instance = serde_json::json!({
+            "image": "${IMAGE}",
+            "mode": "${DEPLOY_MODE}",
+            "port": "${PORT}",
+            "host": "${HOST}.internal"
+        });
+        let contents = instance.to_string();
+        let config = Config {
+            env_placeholders: true,
             ..Default::default()
+        };
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .with_config(&config)
+            .findings()
+            .unwrap();
+
+        // a placeholder is still a string, and only whole-value placeholders are skipped
+        let mut paths: Vec<&str> = findings.iter().map(|f| f.instance_path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/host", "/port"]);
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+        assert_eq!(findings.len(), 4);
+    }
+
+    #[test]
+    fn test_titled_subschema_prefixes_message() {
+        let schema = serde_json::json!({
+            "title": "Service",
+            "definitions": {
+                "docker": { "title": "DockerRuntime", "type": "object" }
+            },
+            "required": ["name"],
+            "properties": {
+                "runtime": { "$ref": "#/definitions/docker" }
+            }
+        });
+        let instance = serde_json::json!({ "runtime": "nginx" });
+        let contents = instance.to_string();
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+        let type_error = findings
+            .iter()
+            .find(|f| f.kind == ValidationKind::Type)
+            .expect("type finding");
+        let required = findings
+            .iter()
+            .find(|f| f.kind == ValidationKind::Required)
+            .expect("required finding");
+
+        assert!(
+            type_error.message.starts_with("[DockerRuntime] "),
+            "{}",
+            type_error.message
+        );
+        // the root title would only repeat the document's own name
+        assert!(!required.message.starts_with('['), "{}", required.message);
+    }
+
+    #[test]
+    fn test_deprecated_property_is_tagged_warning() {
+        let schema = serde_json::json!({
+            "properties": {
+                "runtime": {
+                    "properties": {
+                        "image": { "type": "string" },
+                        "tag": { "type": "string", "deprecated": true }
+                    }
+                }
+            }
+        });
+        let contents = r#"{
+  "runtime": {
+    "image": "nginx",
+    "tag": "latest"
+  }
+}"#;
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let diagnostics = deprecated_properties(&schema, &instance, &Document::new(contents));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[0].tags, Some(vec![DiagnosticTag::DEPRECATED]));
+        assert_eq!(highlighted(contents, diagnostics[0].range), r#""tag""#);
+    }
+
+    #[test]
+    fn test_editing_mode_skips_required_near_cursor() {
+        let schema = serde_json::json!({
+            "properties": {
+                "runtime": {
+                    "required": ["image"],
+                    "properties": { "port": { "type": "integer" } }
+                },
+                "healthcheck": { "required": ["path"] }
+            }
+        });
+        let contents = r#"{
+  "runtime": {
+    "port": "80",
+    "ima": 1
+  },
+  "healthcheck": {
+    "interval": 5
+  }
+}"#;
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+        // typing the key on line 3, inside /runtime
+        let cursor = Position {
+            line: 3,
+            character: 8,
+        };
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .editing_at(cursor)
+            .findings()
+            .unwrap();
+        let required: Vec<&str> = findings
+            .iter()
+            .filter(|f| f.kind == ValidationKind::Required)
+            .map(|f| f.instance_path.as_str())
+            .collect();
+
+        assert_eq!(required, vec!["/healthcheck"]);
+        // the edited object's other violations are kept
+        assert!(
+            findings
+                .iter()
+                .any(|f| f.kind == ValidationKind::Type && f.instance_path == "/runtime/port")
+        );
+    }
+
+    #[test]
+    fn test_nested_errors_link_to_failing_parent() {
+        let schema = serde_json::json!({
+            "properties": {
+                "runtime": {
+                    "required": ["image"],
+                    "properties": { "port": { "type": "integer" } }
+                },
+                "name": { "type": "string" }
+            }
+        });
+        let instance = serde_json::json!({ "name": 1, "runtime": { "port": "80" } });
+        let contents = serde_json::to_string_pretty(&instance).unwrap();
+        let uri = Url::parse("file:///service.json").unwrap();
+
+        let mut diagnostics = SchemaValidator::new(&schema, &instance, &contents)
+            .validate()
+            .unwrap();
+        link_related(&mut diagnostics, &uri);
+
+        let by_source = |source: &str| {
+            diagnostics
+                .iter()
+                .find(|d| d.source.as_deref() == Some(source))
+                .unwrap()
+        };
+        let parent = by_source("/runtime");
+        let related = by_source("/runtime/port")
+            .related_information
+            .as_ref()
+            .expect("linked to /runtime");
+
+        assert_eq!(related[0].location.uri, uri);
+        assert_eq!(related[0].location.range, parent.range);
+        assert_eq!(related[0].message, parent.message);
+        assert_eq!(parent.related_information, None);
+        assert_eq!(by_source("/name").related_information, None);
+    }
+
+    #[test]
+    fn test_empty_document_gets_single_clear_message() {
+        let schema = serde_json::json!({
+            "required": ["name", "port"],
+            "properties": { "name": { "type": "string" } }
+        });
+        let instance = serde_json::json!({});
+        let contents = "\n{}";
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, ValidationKind::Required);
+        assert_eq!(
+            findings[0].message,
+            r#"Document is empty, the schema requires "name", "port""#
+        );
+        assert_eq!(highlighted(contents, findings[0].range), "{");
+    }
+
+    #[test]
+    fn test_external_refs_validate_through_resources() {
+        let schema = serde_json::json!({
+            "properties": { "runtime": { "$ref": "runtime.schema.json" } }
+        });
+        let resources = HashMap::from([(
+            "json-schema:///runtime.schema.json".to_string(),
+            Arc::new(serde_json::json!({ "required": ["image"] })),
+        )]);
+        let instance = serde_json::json!({ "runtime": {} });
+        let contents = instance.to_string();
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .with_resources(&resources)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, ValidationKind::Required);
+        assert_eq!(findings[0].instance_path, "/runtime");
+    }
+
+    #[test]
+    fn test_identical_array_errors_collapse_into_summary() {
+        let schema = serde_json::json!({
+            "properties": {
+                "ports": { "items": { "required": ["protocol"] } },
+                "hosts": { "items": { "type": "string" } },
+                "labels": { "additionalProperties": { "type": "string" } }
+            }
+        });
+        let ports: Vec<_> = (0..10)
+            .map(|port| serde_json::json!({ "number": port }))
+            .collect();
+        let labels: serde_json::Map<_, _> = (0..10)
+            .map(|idx| (idx.to_string(), serde_json::json!(true)))
+            .collect();
+        let instance = serde_json::json!({
+            "ports": ports,
+            "hosts": [1, 2, 3, 4, 5],
+            "labels": labels
+        });
+        let contents = serde_json::to_string_pretty(&instance).unwrap();
+        let config = Config {
+            collapse_array_errors: Some(3),
+            ..Default::default()
+        };
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .with_config(&config)
+            .findings()
+            .unwrap();
+
+        let ports: Vec<_> = findings
+            .iter()
+            .filter(|f| f.instance_path.starts_with("/ports"))
+            .collect();
+        assert_eq!(ports.len(), 1);
+        assert_eq!(ports[0].instance_path, "/ports");
+        assert_eq!(ports[0].kind, ValidationKind::Required);
+        assert!(
+            ports[0].message.starts_with("10 elements"),
+            "{}",
+            ports[0].message
+        );
+        assert!(highlighted(&contents, ports[0].range).starts_with('['));
+
+        // messages naming each element's value differ, so every element keeps its own diagnostic
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.instance_path.starts_with("/hosts/"))
+                .count(),
+            5
+        );
+        // numeric keys of an object are not array elements
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.instance_path.starts_with("/labels/"))
+                .count(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_multiple_of_names_value_and_divisor() {
+        let schema = serde_json::json!({
+            "properties": {
+                "replicas": { "multipleOf": 3 },
+                "ratio": { "multipleOf": 0.5 }
+            }
+        });
+        let instance = serde_json::json!({ "replicas": 7, "ratio": 0.3 });
+        let contents = instance.to_string();
+
+        let mut messages: Vec<String> = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap()
+            .into_iter()
+            .map(|f| f.message)
+            .collect();
+        messages.sort();
+
+        assert_eq!(
+            messages,
+            vec!["0.3 is not a multiple of 0.5", "7 is not a multiple of 3"]
+        );
+    }
+
+    #[test]
+    fn test_integers_beyond_u64_warn_about_precision() {
+        let contents = r#"{
+  "accountId": 98765432109876543210,
+  "shardId": 18446744073709551615,
+  "offset": -0,
+  "ratio": 0.1
+}"#;
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let diagnostics = precision_loss(&instance, &Document::new(contents));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[0].source.as_deref(), Some("/accountId"));
+        assert_eq!(
+            highlighted(contents, diagnostics[0].range),
+            "98765432109876543210"
+        );
+    }
+
+    #[test]
+    fn test_not_violation_explains_forbidden_schema() {
+        let schema = serde_json::json!({
+            "properties": {
+                "image": { "not": { "title": "Latest tag", "pattern": ":latest$" } },
+                "port": { "not": { "const": 22 } }
+            }
+        });
+        let instance = serde_json::json!({ "image": "nginx:latest", "port": 22 });
+        let contents = instance.to_string();
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+        let message = |path: &str| {
+            findings
+                .iter()
+                .find(|f| f.instance_path == path)
+                .map(|f| f.message.clone())
+                .unwrap()
+        };
+
+        assert_eq!(
+            message("/image"),
+            r#"value must NOT match the forbidden schema "Latest tag""#
+        );
+        assert_eq!(
+            message("/port"),
+            "value must NOT match the forbidden schema"
+        );
+    }
+
+    #[test]
+    fn test_one_of_ambiguous_match_lists_branches() {
+        let schema = serde_json::json!({
+            "properties": {
+                "replicas": { "oneOf": [{ "type": "number" }, { "type": "integer" }] }
+            }
+        });
+        let instance = serde_json::json!({ "replicas": 3 });
+        let contents = instance.to_string();
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "matched 2 of 2 oneOf branches (expected exactly 1): branches #0, #1"
+        );
+    }
+
+    #[test]
+    fn test_one_of_no_match_reports_count() {
+        let schema = serde_json::json!({
+            "oneOf": [{ "type": "number" }, { "type": "boolean" }]
+        });
+        let instance = serde_json::json!("three");
+        let contents = instance.to_string();
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(
+            findings[0].message,
+            "matched 0 of 2 oneOf branches (expected exactly 1)"
+        );
+    }
+
+    fn highlighted(contents: &str, range: Range) -> String {
+        let start = crate::line_number::to_index(contents, range.start.line, range.start.character)
+            .unwrap();
+        let end =
+            crate::line_number::to_index(contents, range.end.line, range.end.character).unwrap();
+        contents[start..end].to_string()
+    }
+
+    #[test]
+    fn test_length_keywords_highlight_whole_literal() {
+        let schema = serde_json::json!({
+            "properties": {
+                "ports": { "type": "array", "minItems": 3 },
+                "service": { "type": "string", "maxLength": 3 }
+            }
+        });
+        let contents = "{\n  \"service\": \"billing\",\n  \"ports\": [\n    80,\n    443\n  ]\n}";
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+        let range_of = |path: &str| {
+            findings
+                .iter()
+                .find(|f| f.instance_path == path)
+                .map(|f| f.range)
+                .unwrap()
+        };
+
+        assert_eq!(highlighted(contents, range_of("/service")), r#""billing""#);
+        assert_eq!(
+            highlighted(contents, range_of("/ports")),
+            "[\n    80,\n    443\n  ]"
+        );
+    }
+
+    #[test]
+    fn test_null_document_underlines_null_literal() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../schemas/service.schema.json")).unwrap();
+        let contents = "\n  null\n";
+
+        let diagnostics = crate::validate_liberally(&schema, contents).unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(highlighted(contents, diagnostics[0].range), "null");
+    }
+
+    #[test]
+    fn test_property_names_violation_highlights_offending_key() {
+        let schema = serde_json::json!({
+            "properties": {
+                "labels": { "propertyNames": { "pattern": "^[a-z]+$" } }
+            }
+        });
+        let contents =
+            "{\n  \"labels\": {\n    \"team\": \"core\",\n    \"Cost-Center\": \"42\"\n  }\n}";
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].kind,
+            ValidationKind::Other("propertyNames".into())
+        );
+        assert_eq!(highlighted(contents, findings[0].range), r#""Cost-Center""#);
+    }
+
+    #[test]
+    fn test_fractional_number_for_integer_type_has_specific_message() {
+        let schema = serde_json::json!({
+            "properties": { "replicas": { "type": "integer" } }
+        });
+        let contents = r#"{ "replicas": 1.5 }"#;
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "expected integer, got a number with a fractional part"
+        );
+    }
+
+    #[test]
+    fn test_raw_errors_keep_jsonschema_details() {
+        let schema = serde_json::json!({
+            "required": ["name"],
+            "properties": { "port": { "type": "integer", "minimum": 1 } }
+        });
+        let instance = serde_json::json!({ "port": 0 });
+
+        let errors: Vec<_> = iter_raw_errors(&schema, &instance).unwrap().collect();
+
+        assert_eq!(errors.len(), 2);
+        let minimum = errors
+            .iter()
+            .find(|e| matches!(e.kind(), ValidationErrorKind::Minimum { .. }))
+            .expect("minimum error");
+        assert_eq!(minimum.instance().as_ref(), &serde_json::json!(0));
+        assert_eq!(minimum.schema_path().as_str(), "/properties/port/minimum");
+
+        let invalid = serde_json::json!({ "type": 12 });
+        assert!(matches!(
+            iter_raw_errors(&invalid, &instance),
+            Err(SchemaValidationError::ValidatorCompilationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_compiled_schema_validates_in_parallel() {
+        fn assert_shareable<T: Send + Sync>() {}
+        assert_shareable::<CompiledSchema>();
+
+        let compiled = CompiledSchema::new(serde_json::json!({
+            "properties": { "port": { "type": "integer" } }
+        }))
+        .unwrap();
+        let documents: Vec<String> = (0..32)
+            .map(|idx| match idx % 2 {
+                0 => format!("{{ \"port\": {} }}", idx),
+                _ => format!("{{ \"port\": \"{}\" }}", idx),
+            })
+            .collect();
+
+        let error_counts: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = documents
+                .iter()
+                .map(|contents| scope.spawn(|| compiled.validate(contents).unwrap().len()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        for (idx, count) in error_counts.iter().enumerate() {
+            assert_eq!(*count, idx % 2, "document {}", idx);
         }
+        assert_eq!(
+            compiled.validate(&documents[1]).unwrap(),
+            crate::validate_liberally(compiled.json_schema(), &documents[1]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_contains_failures_count_matching_elements() {
+        let schema = serde_json::json!({
+            "properties": {
+                "ports": {
+                    "contains": { "title": "HttpPort", "properties": { "name": { "const": "http" } } }
+                },
+                "replicas": {
+                    "contains": { "type": "integer", "minimum": 2 },
+                    "minContains": 2
+                }
+            }
+        });
+        let contents = r#"{
+  "ports": [{ "name": "grpc" }, { "name": "admin" }],
+  "replicas": [1, 3, 1]
+}"#;
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+        let message = |path: &str| {
+            findings
+                .iter()
+                .find(|f| f.instance_path == path)
+                .map(|f| f.message.as_str())
+        };
+
+        assert_eq!(
+            message("/ports"),
+            Some("no element matches the required shape \"HttpPort\"")
+        );
+        assert_eq!(
+            message("/replicas"),
+            Some("1 of 3 elements match the required shape, at least 2 required")
+        );
+        let ports = findings
+            .iter()
+            .find(|f| f.instance_path == "/ports")
+            .unwrap();
+        assert_eq!(
+            highlighted(contents, ports.range),
+            r#"[{ "name": "grpc" }, { "name": "admin" }]"#
+        );
+    }
+
+    #[test]
+    fn test_dangling_refs_are_flagged_in_schema() {
+        let schema_contents = r##"{
+  "definitions": { "Foo": { "type": "string" } },
+  "properties": {
+    "name": { "$ref": "#/definitions/Fooo" },
+    "alias": { "$ref": "#/definitions/Foo" }
+  }
+}"##;
+        let schema: serde_json::Value = serde_json::from_str(schema_contents).unwrap();
+
+        let diagnostics = dangling_refs(&schema, schema_contents);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start.line, 3);
+        assert_eq!(
+            diagnostics[0].source.as_deref(),
+            Some("/properties/name/$ref")
+        );
+        assert!(diagnostics[0].message.contains("#/definitions/Fooo"));
+    }
+
+    #[test]
+    fn test_unknown_formats_are_flagged_in_schema() {
+        let schema_contents = r#"{
+  "properties": {
+    "image": { "type": "string", "format": "docker-image" },
+    "site": { "type": "string", "format": "uri" },
+    "format": { "type": "string", "default": { "format": "not-a-schema" } }
+  },
+  "$defs": {
+    "tag": { "format": "semver" },
+    "id": { "format": "uuid" }
+  }
+}"#;
+        let schema: serde_json::Value = serde_json::from_str(schema_contents).unwrap();
+
+        let mut diagnostics = unknown_formats(&schema, schema_contents);
+        diagnostics.sort_by_key(|d| d.range.start);
+
+        let found: Vec<(u32, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.source.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(2, "/properties/image/format"), (7, "/$defs/tag/format")]
+        );
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert!(diagnostics[0].message.contains("docker-image"));
+
+        // draft 7 has no `uuid` format yet
+        let draft7 = serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "properties": { "id": { "format": "uuid" } }
+        });
+        let diagnostics = unknown_formats(&draft7, &draft7.to_string());
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("uuid"));
+
+        // compiling a schema reports them too, for the documents validated against it
+        let compiled = CompiledSchema::new(schema).unwrap();
+        let notices = compiled.unknown_format_notices("service.schema");
+        assert_eq!(notices.len(), 2);
+        assert!(notices.iter().all(|n| {
+            n.message
+                .starts_with("Schema service.schema uses unknown format")
+        }));
+    }
+
+    #[test]
+    fn test_same_keyword_on_one_value_is_reported_once() {
+        let schema = serde_json::json!({
+            "required": ["name", "service"],
+            "$defs": {
+                "port": { "anyOf": [{ "type": "integer" }, { "type": "null" }] },
+                "host": { "pattern": "^[a-z.]+$" }
+            },
+            "properties": {
+                "port": {
+                    "$ref": "#/$defs/port",
+                    "anyOf": [{ "type": "integer", "minimum": 1 }, { "type": "null" }]
+                },
+                "host": { "$ref": "#/$defs/host", "pattern": "^api\\." }
+            }
+        });
+        let instance = serde_json::json!({ "port": "http", "host": "WEB" });
+        let contents = instance.to_string();
+
+        let findings = SchemaValidator::new(&schema, &instance, &contents)
+            .findings()
+            .unwrap();
+        let at = |pointer: &str| -> Vec<&ValidationFinding> {
+            findings
+                .iter()
+                .filter(|f| f.instance_path == pointer)
+                .collect()
+        };
+
+        // both `anyOf`s fail the value, one is reported
+        assert_eq!(at("/port").len(), 1);
+        assert!(at("/port")[0].message.contains("anyOf"));
+        // the longer, more specific pattern message wins
+        assert_eq!(at("/host").len(), 1);
+        assert!(at("/host")[0].message.contains("^[a-z.]+$"));
+        // both missing properties survive, they are different keys
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.kind == ValidationKind::Required)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_forced_draft_rejecting_schema_is_an_error() {
+        // `exclusiveMinimum` is a boolean in draft 4
+        let schema = serde_json::json!({ "properties": { "n": { "exclusiveMinimum": 5 } } });
+        let config = Config {
+            draft: Some(SchemaDraft::Draft4),
+            ..Config::default()
+        };
+
+        assert!(matches!(
+            crate::validate_with_config(&schema, r#"{"n":1}"#, &config),
+            Err(SchemaValidationError::ValidatorCompilationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_detects_declared_draft() {
+        let draft7 = serde_json::json!({ "$schema": "http://json-schema.org/draft-07/schema#" });
+        let draft2020 =
+            serde_json::json!({ "$schema": "https://json-schema.org/draft/2020-12/schema" });
+        let custom = serde_json::json!({ "$schema": "https://example.com/meta" });
+
+        assert_eq!(detect_draft(&draft7), Some(jsonschema::Draft::Draft7));
+        assert_eq!(
+            detect_draft(&draft2020),
+            Some(jsonschema::Draft::Draft202012)
+        );
+        assert_eq!(detect_draft(&custom), None);
+    }
+
+    #[test]
+    fn test_key_with_slash_resolves_through_escaped_pointer() {
+        let schema = serde_json::json!({
+            "properties": { "a/b": { "type": "string" } }
+        });
+        let contents = "{\n  \"service\": \"api\",\n  \"a/b\": 1\n}";
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings[0].instance_path, "/a~1b");
+        assert_eq!(findings[0].range.start.line, 2);
+    }
+
+    #[test]
+    fn test_escaped_keys_resolve_to_their_value_range() {
+        let schema = serde_json::json!({
+            "properties": {
+                "x/y": {
+                    "properties": {
+                        "a~b": { "properties": { "a/b": { "type": "string" } } }
+                    }
+                }
+            }
+        });
+        // the same key occurs earlier in the text, outside the failing object
+        let contents =
+            "{\n  \"a/b\": 2,\n  \"x/y\": {\n    \"a~b\": {\n      \"a/b\": 1\n    }\n  }\n}";
+        let instance: serde_json::Value = serde_json::from_str(contents).unwrap();
+
+        let findings = SchemaValidator::new(&schema, &instance, contents)
+            .findings()
+            .unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].instance_path, "/x~1y/a~0b/a~1b");
+        assert_eq!(findings[0].range.start.line, 4);
+    }
+
+    #[test]
+    fn test_missing_field_maps_to_required_kind() {
+        // not empty, an empty document gets the single empty-document diagnostic instead
+        let findings = findings_for(serde_json::json!({ "other": 1 }));
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, ValidationKind::Required);
     }
 }
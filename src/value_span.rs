@@ -0,0 +1,279 @@
+use tracing::{instrument, trace};
+
+use crate::introspection;
+
+/// Byte span `[start, end)` within the raw file contents
+pub type Span = (usize, usize);
+
+/// A single `"key": value` entry of an object literal, or `key: value` for the unquoted
+/// keys JSON5 allows
+#[derive(Debug, Clone)]
+pub(crate) struct Member {
+    pub key: String,
+    pub key_span: Span,
+    pub value_span: Span,
+}
+
+/// Locates the value at `json_pointer` by walking the document structure
+/// Returns None when the pointer does not exist in the text
+#[instrument(skip(raw_file_contents), fields(pointer = json_pointer))]
+pub(crate) fn locate(json_pointer: &str, raw_file_contents: &str) -> Option<Span> {
+    let start = skip_whitespace(raw_file_contents, 0);
+    let mut span = (start, value_end(raw_file_contents, start)?);
+
+    for segment in json_pointer.split('/').skip(1) {
+        let segment = introspection::unescape(segment);
+        span = child(raw_file_contents, span.0, &segment)?.value_span;
+        trace!(segment = %segment, start = span.0, end = span.1, "Located segment");
+    }
+
+    Some(span)
+}
+
+/// Locates the object member (or array element) the last segment of `json_pointer` names
+/// Unlike `locate` this keeps the key span, for highlighting keys instead of values
+#[instrument(skip(raw_file_contents), fields(pointer = json_pointer))]
+pub(crate) fn locate_member(json_pointer: &str, raw_file_contents: &str) -> Option<Member> {
+    let (parent, segment) = json_pointer.rsplit_once('/')?;
+    let (parent_start, _) = locate(parent, raw_file_contents)?;
+    child(
+        raw_file_contents,
+        parent_start,
+        &introspection::unescape(segment),
+    )
+}
+
+/// Locates the member or element for `segment` in the container starting at `container_start`
+/// Array elements are returned with an empty key and their value span as key span
+pub(crate) fn child(
+    raw_file_contents: &str,
+    container_start: usize,
+    segment: &str,
+) -> Option<Member> {
+    match raw_file_contents.as_bytes().get(container_start)? {
+        b'{' => members(raw_file_contents, container_start)?
+            .into_iter()
+            // duplicate keys resolve to the last occurrence, like serde_json
+            .rfind(|member| member.key == segment),
+        b'[' => {
+            let index: usize = segment.parse().ok()?;
+            let value_span = *elements(raw_file_contents, container_start)?.get(index)?;
+            Some(Member {
+                key: String::new(),
+                key_span: value_span,
+                value_span,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// All members of the object literal whose `{` is at `object_start`
+pub(crate) fn members(raw_file_contents: &str, object_start: usize) -> Option<Vec<Member>> {
+    let bytes = raw_file_contents.as_bytes();
+    let mut members = Vec::new();
+    let mut at = skip_whitespace(raw_file_contents, object_start + 1);
+
+    while *bytes.get(at)? != b'}' {
+        let (key, key_end) = match string_end(raw_file_contents, at) {
+            Some(key_end) => (
+                serde_json::from_str(&raw_file_contents[at..key_end]).ok()?,
+                key_end,
+            ),
+            None => {
+                let key_end = bare_key_end(raw_file_contents, at)?;
+                trace!(start = at, "Read unquoted key");
+                (raw_file_contents[at..key_end].to_string(), key_end)
+            }
+        };
+        let key_span = (at, key_end);
+
+        at = skip_whitespace(raw_file_contents, key_end);
+        if *bytes.get(at)? != b':' {
+            return None;
+        }
+
+        let value_start = skip_whitespace(raw_file_contents, at + 1);
+        let value_span = (value_start, value_end(raw_file_contents, value_start)?);
+        members.push(Member {
+            key,
+            key_span,
+            value_span,
+        });
+
+        at = skip_whitespace(raw_file_contents, value_span.1);
+        if *bytes.get(at)? == b',' {
+            at = skip_whitespace(raw_file_contents, at + 1);
+        }
+    }
+
+    Some(members)
+}
+
+/// Exclusive end of the unquoted (JSON5) key starting at `start`, an identifier name
+/// None when no identifier starts there
+fn bare_key_end(raw_file_contents: &str, start: usize) -> Option<usize> {
+    let len = raw_file_contents[start..]
+        .find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '$'))
+        .unwrap_or(raw_file_contents.len() - start);
+    (len > 0).then_some(start + len)
+}
+
+/// Value spans of all elements of the array literal whose `[` is at `array_start`
+pub(crate) fn elements(raw_file_contents: &str, array_start: usize) -> Option<Vec<Span>> {
+    let bytes = raw_file_contents.as_bytes();
+    let mut elements = Vec::new();
+    let mut at = skip_whitespace(raw_file_contents, array_start + 1);
+
+    while *bytes.get(at)? != b']' {
+        let end = value_end(raw_file_contents, at)?;
+        elements.push((at, end));
+
+        at = skip_whitespace(raw_file_contents, end);
+        if *bytes.get(at)? == b',' {
+            at = skip_whitespace(raw_file_contents, at + 1);
+        }
+    }
+
+    Some(elements)
+}
+
+/// Exclusive end of the value starting at `start`
+/// Strings, objects and arrays are matched to their terminator, scalars run until a delimiter
+pub(crate) fn value_end(raw_file_contents: &str, start: usize) -> Option<usize> {
+    let bytes = raw_file_contents.as_bytes();
+
+    match bytes.get(start)? {
+        b'"' => string_end(raw_file_contents, start),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut at = start;
+            while at < bytes.len() {
+                match bytes[at] {
+                    b'"' => {
+                        at = string_end(raw_file_contents, at)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(at + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                at += 1;
+            }
+            None
+        }
+        _ => Some(
+            bytes[start..]
+                .iter()
+                .position(|b| matches!(b, b',' | b'}' | b']') || b.is_ascii_whitespace())
+                .map_or(bytes.len(), |len| start + len),
+        ),
+    }
+}
+
+/// Exclusive end of the string literal whose opening quote is at `start`
+/// Escaped quotes do not terminate the string
+pub(crate) fn string_end(raw_file_contents: &str, start: usize) -> Option<usize> {
+    let bytes = raw_file_contents.as_bytes();
+    if *bytes.get(start)? != b'"' {
+        return None;
+    }
+
+    let mut at = start + 1;
+    while at < bytes.len() {
+        match bytes[at] {
+            b'\\' => at += 2,
+            b'"' => return Some(at + 1),
+            _ => at += 1,
+        }
+    }
+
+    None
+}
+
+/// First index from `from` on that is neither whitespace nor inside a `//` or `/* */`
+/// comment, which JSONC and JSON5 documents may have between tokens
+pub(crate) fn skip_whitespace(raw_file_contents: &str, from: usize) -> usize {
+    let bytes = raw_file_contents.as_bytes();
+    let mut at = from;
+    loop {
+        match (bytes.get(at), bytes.get(at + 1)) {
+            (Some(byte), _) if byte.is_ascii_whitespace() => at += 1,
+            (Some(b'/'), Some(b'/')) => {
+                at = raw_file_contents[at..]
+                    .find('\n')
+                    .map_or(bytes.len(), |len| at + len);
+            }
+            (Some(b'/'), Some(b'*')) => {
+                at = raw_file_contents[at + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |len| at + 2 + len + 2);
+            }
+            _ => return at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"{
+  "service": "api",
+  "runtime": { "type": "docker", "docker": { "image": "nginx" } },
+  "ports": [ { "containerPort": 8080 }, { "containerPort": 9090 } ]
+}"#;
+
+    fn text_at(pointer: &str) -> &'static str {
+        let (start, end) = locate(pointer, DOCUMENT).expect("pointer exists");
+        &DOCUMENT[start..end]
+    }
+
+    #[test]
+    fn test_locates_scalars_objects_and_array_elements() {
+        assert_eq!(text_at("/service"), r#""api""#);
+        assert_eq!(text_at("/runtime/docker"), r#"{ "image": "nginx" }"#);
+        assert_eq!(text_at("/ports/1/containerPort"), "9090");
+        assert!(locate("/ports/2", DOCUMENT).is_none());
+    }
+
+    #[test]
+    fn test_locates_member_key() {
+        let member = locate_member("/runtime/docker", DOCUMENT).expect("member exists");
+
+        assert_eq!(
+            &DOCUMENT[member.key_span.0..member.key_span.1],
+            r#""docker""#
+        );
+        assert!(locate_member("", DOCUMENT).is_none());
+    }
+
+    #[test]
+    fn test_root_pointer_spans_whole_document() {
+        assert_eq!(text_at(""), DOCUMENT);
+    }
+
+    #[test]
+    fn test_escaped_quotes_and_newlines_stay_inside_string() {
+        let contents = "{\n  \"motd\": \"say \\\"hi\\\" \\\\\",\n  \"banner\": \"line one\\n\\\"port\\\": 1\",\n  \"port\": \"80\"\n}";
+        let text =
+            |pointer: &str| locate(pointer, contents).map(|(start, end)| &contents[start..end]);
+
+        assert_eq!(text("/motd"), Some(r#""say \"hi\" \\""#));
+        assert_eq!(text("/banner"), Some(r#""line one\n\"port\": 1""#));
+        // the escaped "port" inside the banner is not the key
+        assert_eq!(text("/port"), Some(r#""80""#));
+        assert_eq!(
+            crate::json_pointer::into_range("/port", contents)
+                .unwrap()
+                .start
+                .line,
+            3
+        );
+    }
+}